@@ -0,0 +1,127 @@
+//! Compile time checked URIs for [auris](https://docs.rs/auris)
+//!
+//! `uri!` parses its argument while compiling, so a malformed URI is a
+//! compile error instead of a runtime one, and expands to the parsed
+//! `URI<&'static str>`.
+//!
+//! ```
+//! use auris_macros::uri;
+//!
+//! let api = uri!("https://example.com/api?v=2");
+//!
+//! assert_eq!(api.authority.host, "example.com");
+//! assert_eq!(api.path, Some(vec!["api"]));
+//! ```
+//!
+//! ```compile_fail
+//! use auris_macros::uri;
+//!
+//! let broken = uri!("https://example.com:99999/");
+//! ```
+//!
+//! This is a separate crate because it uses the parser in `auris` itself,
+//! so `auris` can't depend on it.
+extern crate proc_macro;
+
+use auris::{PathKind, UserInfo, URI};
+use proc_macro::{TokenStream, TokenTree};
+
+/// Validate a URI literal at compile time, expanding to a `URI<&'static str>`
+#[proc_macro]
+pub fn uri(input: TokenStream) -> TokenStream {
+    let expanded = match string_literal(input) {
+        Ok(literal) => match literal.parse::<URI<String>>() {
+            Ok(uri) => construct(&uri),
+            Err(e) => compile_error(&format!("invalid URI: {}", e)),
+        },
+        Err(message) => compile_error(message),
+    };
+    expanded.parse().expect("generated code is valid")
+}
+
+fn compile_error(message: &str) -> String {
+    format!("compile_error!({:?})", message)
+}
+
+/// The value of the single string literal in `input`
+fn string_literal(input: TokenStream) -> Result<String, &'static str> {
+    const EXPECTED: &str = "uri! takes a single string literal";
+    let mut tokens = input.into_iter();
+    let literal = match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Literal(literal)), None) => literal.to_string(),
+        _ => return Err(EXPECTED),
+    };
+    if let Some(raw) = literal.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        return Ok(raw[hashes + 1..raw.len() - hashes - 1].to_string());
+    }
+    let quoted = literal
+        .strip_prefix('"')
+        .and_then(|l| l.strip_suffix('"'))
+        .ok_or(EXPECTED)?;
+    unescape(quoted).ok_or("uri! only supports simple escapes")
+}
+
+/// Undo the escapes of a string literal that can appear in a URI
+fn unescape(quoted: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            '"' => unescaped.push('"'),
+            '\'' => unescaped.push('\''),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+/// Rust code constructing `uri` with `&'static str`s
+fn construct(uri: &URI<String>) -> String {
+    let userinfo = match uri.authority.userinfo.as_ref() {
+        Some(UserInfo::User(u)) => format!("Some(::auris::UserInfo::User({:?}))", u),
+        Some(UserInfo::UserAndPassword(u, p)) => {
+            format!("Some(::auris::UserInfo::UserAndPassword({:?}, {:?}))", u, p)
+        }
+        None => "None".to_string(),
+    };
+    let path = match uri.path.as_ref() {
+        Some(path) => format!("Some(vec!{:?})", path),
+        None => "None".to_string(),
+    };
+    let path_kind = match uri.path_kind {
+        PathKind::AfterAuthority => "AfterAuthority",
+        PathKind::Absolute => "Absolute",
+        PathKind::Rootless => "Rootless",
+    };
+    let qs = match uri.qs.as_ref() {
+        Some(qs) => {
+            let pairs: Vec<_> = qs.iter().collect();
+            format!("Some(vec!{:?}.into_iter().collect())", pairs)
+        }
+        None => "None".to_string(),
+    };
+    format!(
+        "::auris::URI::<&'static str> {{
+            scheme: {:?},
+            authority: ::auris::Authority {{ host: {:?}, userinfo: {}, port: {:?} }},
+            path: {},
+            path_kind: ::auris::PathKind::{},
+            qs: {},
+            fragment: {:?},
+        }}",
+        uri.scheme,
+        uri.authority.host,
+        userinfo,
+        uri.authority.port,
+        path,
+        path_kind,
+        qs,
+        uri.fragment,
+    )
+}
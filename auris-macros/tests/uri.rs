@@ -0,0 +1,23 @@
+use auris::{PathKind, UserInfo, URI};
+use auris_macros::uri;
+
+#[test]
+fn test_uri_macro_matches_runtime_parsing() {
+    let uri: URI<&'static str> = uri!("postgres://app:pw@db.internal:5432/prod?sslmode=require#x");
+    let parsed = "postgres://app:pw@db.internal:5432/prod?sslmode=require#x"
+        .parse::<URI<String>>()
+        .unwrap();
+
+    assert_eq!(uri, parsed.as_borrowed());
+    assert_eq!(
+        uri.authority.userinfo,
+        Some(UserInfo::UserAndPassword("app", "pw"))
+    );
+}
+
+#[test]
+fn test_uri_macro_literals() {
+    assert_eq!(uri!(r"urn:isbn:0451450523").path_kind, PathKind::Rootless);
+    assert_eq!(uri!(r#"http://h/"q""#).path, Some(vec!["\"q\""]));
+    assert_eq!(uri!("http://h/\"q\"").path, Some(vec!["\"q\""]));
+}
@@ -14,5 +14,26 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, criterion_benchmark);
+fn adversarial_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Adversarial URI Parsing");
+
+    let many_segments = format!("foo://host{}", "/a".repeat(4000));
+    let many_pairs = format!("foo://host/?{}", "k=v&".repeat(4000));
+    let long_host = format!("foo://{}/", "a".repeat(64 * 1024));
+
+    for (name, string) in [
+        ("many path segments", &many_segments),
+        ("many query pairs", &many_pairs),
+        ("long host", &long_host),
+    ]
+    .iter()
+    {
+        group.throughput(Throughput::Bytes(string.len() as u64));
+        group.bench_function(*name, |b| {
+            b.iter(|| uri(string));
+        });
+    }
+}
+
+criterion_group!(benches, criterion_benchmark, adversarial_benchmark);
 criterion_main!(benches);
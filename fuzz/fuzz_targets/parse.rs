@@ -0,0 +1,20 @@
+//! Parsing arbitrary input returns an error rather than panicking, and
+//! whatever parses reparses from its `Display` form to an equal URI
+//!
+//! ```notrust
+//! cargo +nightly fuzz run parse fuzz/corpus/parse
+//! ```
+#![no_main]
+use auris::{ParseOptions, URI};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let input = String::from_utf8_lossy(data);
+    let lenient = ParseOptions::default().numeric_hosts(true);
+    let _ = URI::parse_with(&input, &lenient);
+    if let Ok(uri) = input.parse::<URI<String>>() {
+        let displayed = uri.to_string();
+        let reparsed = displayed.parse::<URI<String>>();
+        assert_eq!(reparsed.ok(), Some(uri), "{:?} as {:?}", input, displayed);
+    }
+});
@@ -0,0 +1,355 @@
+//! Building URIs one component at a time
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::{URIBuilder, URI};
+//!
+//! let uri = URIBuilder::new()
+//!     .scheme("https")
+//!     .host("example.com")
+//!     .path("/api/v1")
+//!     .query_pair("page", "2")
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!("https://example.com/api/v1?page=2", format!("{}", uri));
+//! ```
+use crate::parsers::is_valid_scheme;
+use crate::{AurisParseErrorKind, Authority, ParseError, PathKind, QueryMap, UserInfo, URI};
+use std::fmt;
+use std::net::Ipv6Addr;
+
+/// A component given to a [`URIBuilder`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Field {
+    Scheme,
+    Userinfo,
+    Host,
+    Path,
+    Query,
+    Fragment,
+}
+
+/// A component that can't be written as given, with the reason
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FieldError {
+    pub field: Field,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.field, self.reason)
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+/// Fails with [`AurisParseErrorKind::Rejected`] and the reason
+impl From<FieldError> for ParseError {
+    fn from(error: FieldError) -> Self {
+        AurisParseErrorKind::Rejected(error.reason).into()
+    }
+}
+
+/// Builds a [`URI`] from its components, checking that each can be
+/// written without changing the meaning of the others
+///
+/// Components are given as they appear in the URI, already percent
+/// encoded.
+#[derive(Debug, Clone, Default)]
+pub struct URIBuilder {
+    scheme: String,
+    userinfo: Option<UserInfo<String>>,
+    host: Option<String>,
+    port: Option<u16>,
+    path: Vec<String>,
+    path_absolute: bool,
+    qs: Option<QueryMap<String, String>>,
+    fragment: Option<String>,
+}
+
+impl URI<String> {
+    /// A builder starting out with the components of this URI
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "https://example.com/a?b=c".parse::<URI<String>>().unwrap();
+    /// let moved = uri.to_builder().port(8443).fragment("top").build().unwrap();
+    ///
+    /// assert_eq!("https://example.com:8443/a?b=c#top", format!("{}", moved));
+    /// ```
+    pub fn to_builder(&self) -> URIBuilder {
+        let has_authority = self.has_authority();
+        URIBuilder {
+            scheme: self.scheme.clone(),
+            userinfo: self.authority.userinfo.clone(),
+            host: Some(self.authority.host.clone()).filter(|_| has_authority),
+            port: self.authority.port,
+            path: self.path.clone().unwrap_or_default(),
+            path_absolute: self.path_kind != PathKind::Rootless,
+            qs: self.qs.clone(),
+            fragment: self.fragment.clone(),
+        }
+    }
+}
+
+/// Whether `value` contains whitespace or any of `delimiters`
+fn contains_any(value: &str, delimiters: &str) -> bool {
+    value
+        .chars()
+        .any(|c| c.is_whitespace() || delimiters.contains(c))
+}
+
+impl URIBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn scheme(mut self, scheme: &str) -> Self {
+        self.scheme = scheme.to_string();
+        self
+    }
+
+    pub fn userinfo<U: Into<Option<UserInfo<String>>>>(mut self, userinfo: U) -> Self {
+        self.userinfo = userinfo.into();
+        self
+    }
+
+    /// Set the host, giving the URI an authority; `None` removes it
+    ///
+    /// IPv6 addresses are written in brackets.
+    pub fn host<'a, H: Into<Option<&'a str>>>(mut self, host: H) -> Self {
+        self.host = host.into().map(str::to_string);
+        self
+    }
+
+    pub fn port<P: Into<Option<u16>>>(mut self, port: P) -> Self {
+        self.port = port.into();
+        self
+    }
+
+    /// Set the path, with its segments separated by `/`
+    ///
+    /// Without a host the path is absolute if it starts with `/`, and
+    /// rootless otherwise.
+    pub fn path(mut self, path: &str) -> Self {
+        self.path_absolute = path.starts_with('/');
+        let path = path.strip_prefix('/').unwrap_or(path);
+        self.path = if path.is_empty() && !self.path_absolute {
+            Vec::new()
+        } else {
+            path.split('/').map(str::to_string).collect()
+        };
+        self
+    }
+
+    /// Add a query pair, replacing any value `key` already has
+    pub fn query_pair(mut self, key: &str, value: &str) -> Self {
+        self.qs
+            .get_or_insert_with(QueryMap::default)
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Remove the query, including the `?`
+    pub fn clear_query(mut self) -> Self {
+        self.qs = None;
+        self
+    }
+
+    pub fn fragment<'a, F: Into<Option<&'a str>>>(mut self, fragment: F) -> Self {
+        self.fragment = fragment.into().map(str::to_string);
+        self
+    }
+
+    /// Check every component, reporting all that can't be written as
+    /// given rather than just the first
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::builder::Field;
+    /// use auris::URIBuilder;
+    ///
+    /// let errors = URIBuilder::new()
+    ///     .scheme("1http")
+    ///     .host("exa mple.com")
+    ///     .fragment("top")
+    ///     .validate()
+    ///     .unwrap_err();
+    /// let fields: Vec<Field> = errors.iter().map(|e| e.field).collect();
+    ///
+    /// assert_eq!(fields, vec![Field::Scheme, Field::Host]);
+    /// assert_eq!(errors[1].to_string(), "Host: invalid host");
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        let mut reject = |field, reason| errors.push(FieldError { field, reason });
+        if !is_valid_scheme(&self.scheme) {
+            reject(Field::Scheme, "invalid scheme");
+        }
+        let has_authority = self.host.is_some();
+        if !has_authority && (self.userinfo.is_some() || self.port.is_some()) {
+            reject(Field::Host, "userinfo and port need a host");
+        }
+        let valid_userinfo = match self.userinfo.as_ref() {
+            Some(UserInfo::User(user)) => !contains_any(user, ":@/?#"),
+            Some(UserInfo::UserAndPassword(user, password)) => {
+                !contains_any(user, ":@/?#") && !contains_any(password, "@/?#")
+            }
+            None => true,
+        };
+        if !valid_userinfo {
+            reject(Field::Userinfo, "invalid userinfo");
+        }
+        let host = self.host.as_deref().unwrap_or_default();
+        let valid_host = match host.strip_prefix('[') {
+            Some(literal) => literal
+                .strip_suffix(']')
+                .is_some_and(|ip| ip.parse::<Ipv6Addr>().is_ok()),
+            None => !contains_any(host, ":@/?#[]"),
+        };
+        if !valid_host {
+            reject(Field::Host, "invalid host");
+        }
+        // after a host the path has to start with `/`, and without one it
+        // mustn't start with `//`, or it would read as an authority
+        let rootless_after_host = has_authority && !self.path_absolute && !self.path.is_empty();
+        let reads_as_authority =
+            !has_authority && self.path_absolute && self.path.len() > 1 && self.path[0].is_empty();
+        if rootless_after_host
+            || reads_as_authority
+            || self.path.iter().any(|s| contains_any(s, "/?#"))
+        {
+            reject(Field::Path, "invalid path");
+        }
+        let valid_query = self
+            .qs
+            .iter()
+            .flatten()
+            .all(|(k, v)| !contains_any(k, "&=#") && !contains_any(v, "&#"));
+        if !valid_query {
+            reject(Field::Query, "invalid query");
+        }
+        if self
+            .fragment
+            .as_deref()
+            .is_some_and(|f| f.contains(char::is_whitespace))
+        {
+            reject(Field::Fragment, "invalid fragment");
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Check the components and build the URI
+    ///
+    /// Fails with [`AurisParseErrorKind::Rejected`] naming the first
+    /// component that can't be written as given, see
+    /// [`URIBuilder::validate`] for all of them.
+    pub fn build(self) -> Result<URI<String>, ParseError> {
+        if let Err(errors) = self.validate() {
+            return Err(errors[0].into());
+        }
+        let has_authority = self.host.is_some();
+        let path_kind = match (has_authority, self.path_absolute) {
+            (true, _) => PathKind::AfterAuthority,
+            (false, true) => PathKind::Absolute,
+            (false, false) => PathKind::Rootless,
+        };
+        Ok(URI {
+            scheme: self.scheme,
+            authority: Authority {
+                host: self.host.unwrap_or_default(),
+                userinfo: self.userinfo,
+                port: self.port,
+            },
+            path: Some(self.path),
+            path_kind,
+            qs: self.qs,
+            fragment: self.fragment,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_builder_round_trips() {
+        for input in &[
+            "http://u:p@h:8080/a//b/?k=v#f",
+            "http://h",
+            "file:///etc/hosts",
+            "urn:isbn:123",
+            "mailto:",
+            "x:/a",
+            "s://h?",
+        ] {
+            let uri = input.parse::<URI<String>>().unwrap();
+            assert_eq!(uri.to_builder().build().unwrap(), uri, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_build_rejects() {
+        let base = || URIBuilder::new().scheme("http").host("h");
+        let reason = |builder: URIBuilder| match builder.build().unwrap_err().kind() {
+            AurisParseErrorKind::Rejected(reason) => reason,
+            kind => panic!("{:?}", kind),
+        };
+        assert_eq!(reason(base().scheme("1x")), "invalid scheme");
+        assert_eq!(reason(base().host("a/b")), "invalid host");
+        assert_eq!(reason(base().host("[::1")), "invalid host");
+        assert_eq!(
+            reason(base().host(None).port(80)),
+            "userinfo and port need a host"
+        );
+        assert_eq!(
+            reason(base().userinfo(UserInfo::User("a@b".to_string()))),
+            "invalid userinfo"
+        );
+        assert_eq!(reason(base().path("rootless")), "invalid path");
+        assert_eq!(reason(base().path("/a?b")), "invalid path");
+        assert_eq!(reason(base().host(None).path("//a")), "invalid path");
+        assert_eq!(reason(base().query_pair("a&b", "c")), "invalid query");
+        assert_eq!(reason(base().fragment("a b")), "invalid fragment");
+        assert!(base().host("[::1]").path("/").build().is_ok());
+    }
+
+    #[test]
+    fn test_validate_reports_every_field() {
+        let errors = URIBuilder::new()
+            .scheme("")
+            .userinfo(UserInfo::User("a@b".to_string()))
+            .port(80)
+            .path("//a")
+            .query_pair("k#", "v")
+            .fragment("a b")
+            .validate()
+            .unwrap_err();
+        let fields: Vec<Field> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(
+            fields,
+            vec![
+                Field::Scheme,
+                Field::Host,
+                Field::Userinfo,
+                Field::Path,
+                Field::Query,
+                Field::Fragment
+            ]
+        );
+        assert_eq!(errors[1].reason, "userinfo and port need a host");
+        assert!(URIBuilder::new().scheme("http").validate().is_ok());
+    }
+}
@@ -0,0 +1,167 @@
+//! Parsing many URIs at once, such as every line of a log
+//!
+//! # Examples
+//!
+//! ```
+//! let lines = "https://example.com/a\nnot a uri\nftp://files.example.com/pub";
+//! let parsed: Vec<_> = auris::parse_all(lines.lines()).collect();
+//!
+//! assert_eq!(parsed.len(), 3);
+//! assert_eq!(parsed[0].as_ref().unwrap().host_str(), "example.com");
+//! assert!(parsed[1].is_err());
+//! ```
+use crate::{ParseError, ParseOptions, URI};
+use std::borrow::Cow;
+
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// Iterator over the results of parsing each input, see [`parse_all`]
+#[derive(Debug, Clone)]
+pub struct ParseAll<'a, I> {
+    inputs: I,
+    options: Cow<'a, ParseOptions>,
+    /// Reused for the input rewritten by the lenient options, so parsing
+    /// doesn't allocate it again for each input
+    buffer: String,
+}
+
+/// Parse every input, yielding a result per input in the same order
+///
+/// A failed input doesn't stop the others from being parsed.
+pub fn parse_all<I>(inputs: I) -> ParseAll<'static, I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    ParseAll {
+        inputs: inputs.into_iter(),
+        options: Cow::Owned(ParseOptions::default()),
+        buffer: String::new(),
+    }
+}
+
+/// Like [`parse_all`], parsing with `options`
+///
+/// Inputs rewritten by the lenient options, such as
+/// [`ParseOptions::strip_whitespace`], are rewritten into one buffer reused
+/// for every input.
+///
+/// # Examples
+///
+/// ```
+/// use auris::ParseOptions;
+///
+/// let options = ParseOptions::default().strip_whitespace(true);
+/// let hosts: Vec<String> = auris::parse_all_with(vec![" http://a.com ", "http://b.com\n"], &options)
+///     .map(|uri| uri.unwrap().host_str().to_string())
+///     .collect();
+///
+/// assert_eq!(hosts, vec!["a.com", "b.com"]);
+/// ```
+pub fn parse_all_with<I>(inputs: I, options: &ParseOptions) -> ParseAll<'_, I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    ParseAll {
+        inputs: inputs.into_iter(),
+        options: Cow::Borrowed(options),
+        buffer: String::new(),
+    }
+}
+
+impl<I> Iterator for ParseAll<'_, I>
+where
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    type Item = Result<URI<String>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let input = self.inputs.next()?;
+        if !self.options.rewrites_input() {
+            return Some(self.options.parse_preprocessed(input.as_ref()));
+        }
+        self.options
+            .preprocess_into(input.as_ref(), &mut self.buffer);
+        Some(self.options.parse_preprocessed(&self.buffer))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inputs.size_hint()
+    }
+}
+
+/// Parse and canonicalize every input in parallel, on the rayon thread
+/// pool
+///
+/// Each URI is parsed like `FromStr` does and then
+/// [canonicalized](URI::canonicalize). Collecting into a `Vec` keeps the
+/// order of the inputs. Requires the `rayon` feature.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::iter::ParallelIterator;
+///
+/// let urls = vec!["HTTP://Example.com:80/a/../b".to_string(), "bad url".to_string()];
+/// let canonical: Vec<_> = auris::par_canonicalize(urls)
+///     .map(|uri| uri.map(|uri| uri.to_string()).ok())
+///     .collect();
+///
+/// assert_eq!(canonical, vec![Some("http://example.com/b".to_string()), None]);
+/// ```
+#[cfg(feature = "rayon")]
+pub fn par_canonicalize<I>(
+    inputs: I,
+) -> impl ParallelIterator<Item = Result<URI<String>, ParseError>>
+where
+    I: IntoParallelIterator,
+    I::Item: AsRef<str>,
+{
+    inputs.into_par_iter().map(|input| {
+        let mut uri = input.as_ref().parse::<URI<String>>()?;
+        uri.canonicalize();
+        Ok(uri)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_all_keeps_order() {
+        let inputs = vec!["http://a".to_string(), "".to_string(), "urn:x".to_string()];
+        let parsed: Vec<_> = parse_all(&inputs)
+            .map(|r| r.map(|u| u.to_string()).ok())
+            .collect();
+        assert_eq!(parsed[0].as_deref(), Some("http://a"));
+        assert_eq!(parsed[1], None);
+        assert_eq!(parsed[2].as_deref(), Some("urn:x"));
+        assert_eq!(parse_all(&inputs).size_hint(), (3, Some(3)));
+    }
+
+    #[test]
+    fn test_parse_all_with_reuses_the_buffer() {
+        let options = ParseOptions::default()
+            .strip_whitespace(true)
+            .backslash_as_slash(true)
+            .empty_port_as_absent(true);
+        let inputs = [
+            " http://long.example.com:\\a\\b?x=\\ ",
+            "ht\ttp://b\\c",
+            "\n",
+        ];
+        let parsed: Vec<_> = parse_all_with(&inputs, &options)
+            .map(|r| r.map(|u| u.to_string()).ok())
+            .collect();
+        assert_eq!(
+            parsed[0].as_deref(),
+            Some("http://long.example.com/a/b?x=\\")
+        );
+        assert_eq!(parsed[1].as_deref(), Some("http://b/c"));
+        assert_eq!(parsed[2], None);
+    }
+}
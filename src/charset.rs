@@ -0,0 +1,79 @@
+//! Character encodings for percent decoded bytes
+//!
+//! Percent escapes only describe bytes, the character encoding they were
+//! produced with is up to the sender. UTF-8 is the norm, but older systems
+//! still emit URIs in legacy encodings.
+//!
+//! Any `Fn(&[u8]) -> String` is a [`Charset`](trait.Charset.html), so other
+//! encodings can be plugged in, for example with `encoding_rs`:
+//!
+//! ```text
+//! let shift_jis = |bytes: &[u8]| encoding_rs::SHIFT_JIS.decode(bytes).0.into_owned();
+//! uri.decode_query_with(&shift_jis);
+//! ```
+
+/// Turns decoded bytes into text
+pub trait Charset {
+    fn decode(&self, bytes: &[u8]) -> String;
+}
+
+impl<F> Charset for F
+where
+    F: Fn(&[u8]) -> String,
+{
+    fn decode(&self, bytes: &[u8]) -> String {
+        self(bytes)
+    }
+}
+
+/// UTF-8, invalid sequences are replaced with `U+FFFD`
+#[derive(Debug, Clone, Copy)]
+pub struct Utf8;
+
+impl Charset for Utf8 {
+    fn decode(&self, bytes: &[u8]) -> String {
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+}
+
+/// ISO-8859-1, every byte is the code point of the same value
+#[derive(Debug, Clone, Copy)]
+pub struct Latin1;
+
+impl Charset for Latin1 {
+    fn decode(&self, bytes: &[u8]) -> String {
+        bytes.iter().map(|b| char::from(*b)).collect()
+    }
+}
+
+/// Windows-1252, ISO-8859-1 with printable characters in `0x80..=0x9F`
+///
+/// # Examples
+///
+/// ```
+/// use auris::charset::{Charset, Windows1252};
+///
+/// assert_eq!(Windows1252.decode(&[0x80, 0x20, 0xE9]), "€ é");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Windows1252;
+
+/// `0x80..=0x9F` in Windows-1252, unassigned bytes map to the C1 control
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+impl Charset for Windows1252 {
+    fn decode(&self, bytes: &[u8]) -> String {
+        bytes
+            .iter()
+            .map(|b| match b {
+                0x80..=0x9F => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+                _ => char::from(*b),
+            })
+            .collect()
+    }
+}
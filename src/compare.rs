@@ -0,0 +1,175 @@
+//! Comparisons between URIs
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::URI;
+//!
+//! let page = "https://example.com/guide#install".parse::<URI<String>>().unwrap();
+//! let anchor = "https://example.com/guide#usage".parse::<URI<String>>().unwrap();
+//!
+//! assert!(page.is_same_document_reference(&anchor));
+//! ```
+use crate::{schemes, Authority, Host, URI};
+
+/// Compare hosts case insensitively, and IP addresses by value
+fn same_host(a: &str, b: &str) -> bool {
+    match (Host::from_authority_host(a), Host::from_authority_host(b)) {
+        (Some(a), Some(b)) => a.eq_ignore_case(&b),
+        _ => a.eq_ignore_ascii_case(b),
+    }
+}
+
+impl Authority<String> {
+    /// Whether both authorities have the same host, ignoring their
+    /// userinfo and ports
+    ///
+    /// Domains are compared ignoring ASCII case and percent escapes, IP
+    /// addresses by value. Hosts that aren't valid are compared ignoring
+    /// ASCII case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let a = "http://Example.com/".parse::<URI<String>>().unwrap();
+    /// let b = "http://user@EXAMPLE.com:8080/".parse::<URI<String>>().unwrap();
+    /// assert!(a.authority.same_host(&b.authority));
+    ///
+    /// let a = "http://[::1]/".parse::<URI<String>>().unwrap();
+    /// let b = "http://[0:0::1]/".parse::<URI<String>>().unwrap();
+    /// assert!(a.authority.same_host(&b.authority));
+    /// ```
+    pub fn same_host(&self, other: &Authority<String>) -> bool {
+        same_host(&self.host, &other.host)
+    }
+}
+
+impl URI<String> {
+    /// Whether `other` refers to the same document, that is the URIs are
+    /// identical apart from their fragments (RFC 3986 section 4.4)
+    ///
+    /// Following such a reference only moves within the current document,
+    /// it doesn't need a new request.
+    pub fn is_same_document_reference(&self, other: &URI<String>) -> bool {
+        self.eq_ignoring_fragment(other)
+    }
+
+    /// Whether the URIs are equal apart from their fragments
+    ///
+    /// HTTP caches and crawlers treat such URIs as the same resource, as
+    /// the fragment is never sent to the server.
+    /// [`URI::canonical_hash_ignoring_fragment`] is the matching hash.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let a = "https://example.com/page#a".parse::<URI<String>>().unwrap();
+    /// let b = "https://example.com/page#b".parse::<URI<String>>().unwrap();
+    ///
+    /// assert!(a.eq_ignoring_fragment(&b));
+    /// assert_ne!(a, b);
+    /// ```
+    pub fn eq_ignoring_fragment(&self, other: &URI<String>) -> bool {
+        self.scheme == other.scheme
+            && self.authority == other.authority
+            && self.path_kind == other.path_kind
+            && self.path == other.path
+            && self.qs == other.qs
+    }
+
+    /// Whether both URIs have the same origin: scheme, host and port
+    ///
+    /// Schemes and hosts are compared case insensitively, IP addresses by
+    /// value, and a missing port is the scheme's default port. URIs without
+    /// an authority have no origin to share and never match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let page = "https://Example.com/app".parse::<URI<String>>().unwrap();
+    /// let api = "HTTPS://example.com:443/api".parse::<URI<String>>().unwrap();
+    /// let other = "https://example.com:8443/api".parse::<URI<String>>().unwrap();
+    ///
+    /// assert!(page.is_same_origin(&api));
+    /// assert!(!page.is_same_origin(&other));
+    /// ```
+    pub fn is_same_origin(&self, other: &URI<String>) -> bool {
+        let effective_port = |uri: &URI<String>| {
+            uri.authority
+                .port
+                .or_else(|| schemes::default_port(&uri.scheme))
+        };
+        self.has_authority()
+            && other.has_authority()
+            && self.scheme.eq_ignore_ascii_case(&other.scheme)
+            && self.authority.same_host(&other.authority)
+            && effective_port(self) == effective_port(other)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn same_document(a: &str, b: &str) -> bool {
+        let a = a.parse::<URI<String>>().unwrap();
+        let b = b.parse::<URI<String>>().unwrap();
+        a.is_same_document_reference(&b)
+    }
+
+    #[test]
+    fn test_same_document_reference() {
+        assert!(same_document("http://h/a?q=1", "http://h/a?q=1#top"));
+        assert!(same_document("http://h/a#x", "http://h/a#x"));
+        assert!(!same_document("http://h/a?q=1#x", "http://h/a?q=2#x"));
+        assert!(!same_document("http://h/a#x", "http://h/b#x"));
+        assert!(!same_document("http://h/a", "https://h/a"));
+    }
+
+    #[test]
+    fn test_same_host() {
+        let same = |a: &str, b: &str| {
+            let a = a.parse::<URI<String>>().unwrap();
+            let b = b.parse::<URI<String>>().unwrap();
+            a.authority.same_host(&b.authority)
+        };
+        assert!(same("http://a%2Db.com/", "http://A-B.com/"));
+        assert!(same("http://127.0.0.1/", "https://127.0.0.1:8443/"));
+        assert!(!same("http://a.com/", "http://b.com/"));
+        assert!(!same("http://[::ffff:7f00:1]/", "http://127.0.0.1/"));
+        assert!(same("http://a%20b/", "http://A%20B/"));
+    }
+
+    #[test]
+    fn test_eq_ignoring_fragment_matches_hash() {
+        let a = "http://h/a?q=1#x".parse::<URI<String>>().unwrap();
+        let b = "http://h/a?q=1".parse::<URI<String>>().unwrap();
+        assert!(a.eq_ignoring_fragment(&b));
+        assert_eq!(
+            a.canonical_hash_ignoring_fragment(),
+            b.canonical_hash_ignoring_fragment()
+        );
+        assert_ne!(a.canonical_hash(), b.canonical_hash());
+    }
+
+    #[test]
+    fn test_same_origin() {
+        let origin = |a: &str, b: &str| {
+            let a = a.parse::<URI<String>>().unwrap();
+            let b = b.parse::<URI<String>>().unwrap();
+            a.is_same_origin(&b)
+        };
+        assert!(origin("http://[::1]/", "http://[0:0::1]:80/x"));
+        assert!(origin("http://127.0.0.1/", "http://127.0.0.1:80/"));
+        assert!(!origin("http://127.0.0.1/", "http://127.0.0.2/"));
+        assert!(!origin("http://example.com/", "https://example.com/"));
+        assert!(!origin("custom://h/", "custom://h:1/"));
+        assert!(!origin("mailto:a@example.com", "mailto:a@example.com"));
+    }
+}
@@ -0,0 +1,610 @@
+//! Options controlling how URIs are serialized
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::{DisplayOptions, SpaceEncoding, URI};
+//!
+//! let mut uri = "https://example.com/search".parse::<URI<String>>().unwrap();
+//! uri.qs = Some([("q".to_string(), "red shoes".to_string())].iter().cloned().collect());
+//!
+//! let plus = DisplayOptions::default().space_encoding(SpaceEncoding::Plus);
+//! assert_eq!("https://example.com/search?q=red+shoes", uri.to_string_with(&plus));
+//!
+//! let percent = DisplayOptions::default().space_encoding(SpaceEncoding::Percent);
+//! assert_eq!("https://example.com/search?q=red%20shoes", uri.to_string_with(&percent));
+//! ```
+use crate::{schemes, Authority, PathKind, QueryStore, UserInfo, URI};
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+
+/// How spaces in query keys and values are written out
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SpaceEncoding {
+    /// `%20`, as RFC 3986 specifies
+    Percent,
+    /// `+`, as HTML forms (`application/x-www-form-urlencoded`) do
+    Plus,
+}
+
+/// What to do with a slash at the end of the path
+///
+/// Only paths after an authority and absolute paths are changed. Rootless
+/// paths, as in `urn:a:b` or `mailto:jane@example.com`, are opaque data
+/// where a slash isn't a separator.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum TrailingSlash {
+    /// Write the path as it is
+    #[default]
+    Keep,
+    /// Add a slash unless the path already ends with one, so `http://h`
+    /// becomes `http://h/`
+    Force,
+    /// Remove a slash at the end of the path, except the `/` a path
+    /// without an authority consists of
+    Forbid,
+}
+
+/// Options for [`URI::to_string_with`](crate::URI::to_string_with)
+///
+/// The defaults match `Display`.
+#[derive(Debug, Clone)]
+pub struct DisplayOptions {
+    space_encoding: Option<SpaceEncoding>,
+    omit_default_port: bool,
+    trailing_slash: TrailingSlash,
+    empty_query: bool,
+    sort_params: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            space_encoding: None,
+            omit_default_port: false,
+            trailing_slash: TrailingSlash::Keep,
+            empty_query: true,
+            sort_params: false,
+        }
+    }
+}
+
+impl DisplayOptions {
+    /// Write spaces in the query, whether literal or already encoded as
+    /// `%20`, in this form
+    ///
+    /// A `+` is always kept as it is, as it may be a literal plus sign.
+    pub fn space_encoding(mut self, encoding: SpaceEncoding) -> Self {
+        self.space_encoding = Some(encoding);
+        self
+    }
+
+    /// Leave out the port when it is the scheme's default, as in
+    /// `https://example.com:443/`
+    pub fn omit_default_port(mut self, omit: bool) -> Self {
+        self.omit_default_port = omit;
+        self
+    }
+
+    /// Add or remove a slash at the end of the path, which is kept as it
+    /// is by default
+    pub fn trailing_slash(mut self, policy: TrailingSlash) -> Self {
+        self.trailing_slash = policy;
+        self
+    }
+
+    /// Whether a query without pairs is written as a bare `?`, which it is
+    /// by default
+    pub fn empty_query(mut self, include: bool) -> Self {
+        self.empty_query = include;
+        self
+    }
+
+    /// Write the query pairs sorted by key, then value, rather than in map
+    /// order
+    pub fn sort_params(mut self, sort: bool) -> Self {
+        self.sort_params = sort;
+        self
+    }
+
+    fn query_component<'a>(&self, component: &'a str) -> Cow<'a, str> {
+        match self.space_encoding {
+            Some(SpaceEncoding::Percent) if component.contains(' ') => {
+                Cow::Owned(component.replace(' ', "%20"))
+            }
+            Some(SpaceEncoding::Plus) if component.contains(' ') || component.contains("%20") => {
+                Cow::Owned(component.replace(' ', "+").replace("%20", "+"))
+            }
+            _ => Cow::Borrowed(component),
+        }
+    }
+}
+
+impl Authority<String> {
+    /// Append the authority to `out`, as `Display` writes it
+    pub(crate) fn push_to(&self, out: &mut String) {
+        self.push_with_port(out, self.port)
+    }
+
+    /// Append the authority to `out` with `port` in place of its own
+    fn push_with_port(&self, out: &mut String, port: Option<u16>) {
+        match self.userinfo.as_ref() {
+            Some(UserInfo::User(user)) => out.push_str(user),
+            Some(UserInfo::UserAndPassword(user, password)) => {
+                out.push_str(user);
+                out.push(':');
+                out.push_str(password);
+            }
+            None => (),
+        }
+        if self.userinfo.is_some() {
+            out.push('@');
+        }
+        out.push_str(&self.host);
+        if let Some(port) = port {
+            out.push(':');
+            out.push_str(&port.to_string());
+        }
+    }
+
+    /// Write the authority to `out` as `Display` does, without building a
+    /// `String` first
+    pub fn write_to<W: io::Write>(&self, out: &mut W) -> io::Result<()> {
+        match self.userinfo.as_ref() {
+            Some(UserInfo::User(user)) => write!(out, "{}@", user)?,
+            Some(UserInfo::UserAndPassword(user, password)) => {
+                write!(out, "{}:{}@", user, password)?
+            }
+            None => (),
+        }
+        out.write_all(self.host.as_bytes())?;
+        if let Some(port) = self.port {
+            write!(out, ":{}", port)?;
+        }
+        Ok(())
+    }
+
+    /// The length of the authority as written by `Display`
+    pub(crate) fn serialized_len(&self) -> usize {
+        let userinfo = match self.userinfo.as_ref() {
+            Some(UserInfo::User(user)) => user.len() + 1,
+            Some(UserInfo::UserAndPassword(user, password)) => user.len() + password.len() + 2,
+            None => 0,
+        };
+        let port = match self.port {
+            Some(port) => 1 + (port.checked_ilog10().unwrap_or(0) + 1) as usize,
+            None => 0,
+        };
+        userinfo + self.host.len() + port
+    }
+}
+
+impl<Q: QueryStore> URI<String, Q> {
+    /// Convert the URI to a string according to `options`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::{DisplayOptions, TrailingSlash, URI};
+    ///
+    /// let uri = "https://example.com:443/docs?".parse::<URI<String>>().unwrap();
+    /// let options = DisplayOptions::default()
+    ///     .omit_default_port(true)
+    ///     .trailing_slash(TrailingSlash::Force)
+    ///     .empty_query(false);
+    ///
+    /// assert_eq!("https://example.com/docs/", uri.to_string_with(&options));
+    /// ```
+    pub fn to_string_with(&self, options: &DisplayOptions) -> String {
+        let mut formatted = String::with_capacity(self.serialized_len() + 1);
+        formatted.push_str(&self.scheme);
+        if self.path_kind == PathKind::AfterAuthority {
+            formatted.push_str("://");
+            let port = self.authority.port.filter(|port| {
+                !options.omit_default_port || schemes::default_port(&self.scheme) != Some(*port)
+            });
+            self.authority.push_with_port(&mut formatted, port);
+        } else {
+            formatted.push(':');
+        }
+        let mut path: &[String] = self.path.as_deref().unwrap_or_default();
+        let ends_with_slash = path.last().is_some_and(|s| s.is_empty());
+        let mut add_slash = false;
+        match options.trailing_slash {
+            TrailingSlash::Keep => (),
+            _ if self.path_kind == PathKind::Rootless => (),
+            TrailingSlash::Force => {
+                add_slash = !ends_with_slash
+                    && (!path.is_empty() || self.path_kind == PathKind::AfterAuthority);
+            }
+            TrailingSlash::Forbid => {
+                if ends_with_slash && (path.len() > 1 || self.path_kind == PathKind::AfterAuthority)
+                {
+                    path = &path[..path.len() - 1];
+                }
+            }
+        }
+        for (i, segment) in path.iter().enumerate() {
+            if i > 0 || self.path_kind != PathKind::Rootless {
+                formatted.push('/');
+            }
+            formatted.push_str(segment);
+        }
+        if add_slash {
+            formatted.push('/');
+        }
+        if let Some(qs) = self.qs.as_ref() {
+            let mut pairs: Vec<_> = qs.pairs().collect();
+            // otherwise map iteration order decides the order of the pairs
+            if options.sort_params {
+                pairs.sort();
+            }
+            if !pairs.is_empty() || options.empty_query {
+                formatted.push('?');
+            }
+            for (i, (k, v)) in pairs.into_iter().enumerate() {
+                if i > 0 {
+                    formatted.push('&');
+                }
+                formatted.push_str(&options.query_component(k));
+                formatted.push('=');
+                formatted.push_str(&options.query_component(v));
+            }
+        }
+        if let Some(fragment) = self.fragment.as_ref() {
+            formatted.push('#');
+            formatted.push_str(fragment);
+        }
+        formatted
+    }
+
+    /// Write the URI to `out` as `Display` does, without building a
+    /// `String` first
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "https://example.com/a/b?k=v#top".parse::<URI<String>>().unwrap();
+    /// let mut buffer = Vec::new();
+    /// uri.write_to(&mut buffer).unwrap();
+    ///
+    /// assert_eq!(buffer, b"https://example.com/a/b?k=v#top");
+    /// ```
+    pub fn write_to<W: io::Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(self.scheme.as_bytes())?;
+        if self.path_kind == PathKind::AfterAuthority {
+            out.write_all(b"://")?;
+            self.authority.write_to(out)?;
+        } else {
+            out.write_all(b":")?;
+        }
+        for (i, segment) in self.path.iter().flatten().enumerate() {
+            if i > 0 || self.path_kind != PathKind::Rootless {
+                out.write_all(b"/")?;
+            }
+            out.write_all(segment.as_bytes())?;
+        }
+        if let Some(qs) = self.qs.as_ref() {
+            out.write_all(b"?")?;
+            for (i, (k, v)) in qs.pairs().enumerate() {
+                if i > 0 {
+                    out.write_all(b"&")?;
+                }
+                write!(out, "{}={}", k, v)?;
+            }
+        }
+        if let Some(fragment) = self.fragment.as_ref() {
+            write!(out, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+
+    /// The length of the URI as written by `Display`
+    ///
+    /// Exact for the default options, escaping spaces may add to it.
+    fn serialized_len(&self) -> usize {
+        let mut len = self.scheme.len();
+        len += match self.path_kind {
+            PathKind::AfterAuthority => "://".len() + self.authority.serialized_len(),
+            _ => ":".len(),
+        };
+        if let Some(path) = self.path.as_ref() {
+            len += path.iter().map(|segment| segment.len() + 1).sum::<usize>();
+            if self.path_kind == PathKind::Rootless && !path.is_empty() {
+                len -= 1;
+            }
+        }
+        if let Some(qs) = self.qs.as_ref() {
+            // `?` and `=` for every pair, with the `&`s between them
+            len += qs
+                .pairs()
+                .map(|(k, v)| k.len() + v.len() + 2)
+                .sum::<usize>();
+            len += usize::from(qs.pairs().next().is_none());
+        }
+        if let Some(fragment) = self.fragment.as_ref() {
+            len += fragment.len() + 1;
+        }
+        len
+    }
+
+    /// Shorten the URI to at most `max_len` characters, for logs and TUIs
+    ///
+    /// The scheme, host and last path segment stay visible. The query and
+    /// fragment collapse to `…` first, then the middle of the path does.
+    /// If that is still too long the result is cut off with a trailing
+    /// `…`. Userinfo is left out, as with `{:#}`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "https://example.com/a/b/c/d/e/f/z?page=2".parse::<URI<String>>().unwrap();
+    /// assert_eq!(uri.display_truncated(80), "https://example.com/a/b/c/d/e/f/z?page=2");
+    /// assert_eq!(uri.display_truncated(30), "https://example.com/a/b/…/z?…");
+    /// ```
+    pub fn display_truncated(&self, max_len: usize) -> String {
+        let mut head = self.scheme.clone();
+        if self.path_kind == PathKind::AfterAuthority {
+            head.push_str("://");
+            head.push_str(&self.authority.host);
+            if let Some(port) = self.authority.port {
+                head.push(':');
+                head.push_str(&port.to_string());
+            }
+        } else {
+            head.push(':');
+        }
+        let segments: Vec<&str> = self.path.iter().flatten().map(String::as_str).collect();
+        let path = |kept: &[&str], elided: bool| {
+            let mut path = String::new();
+            for (i, segment) in kept.iter().enumerate() {
+                if i > 0 || self.path_kind != PathKind::Rootless {
+                    path.push('/');
+                }
+                path.push_str(segment);
+            }
+            if elided {
+                path.push_str("/…");
+                if let Some(last) = segments.last() {
+                    path.push('/');
+                    path.push_str(last);
+                }
+            }
+            path
+        };
+        let fits = |s: &str| s.chars().count() <= max_len;
+
+        let mut full = head.clone();
+        full.push_str(&path(&segments, false));
+        let mut tail = String::new();
+        if let Some(qs) = self.qs.as_ref() {
+            full.push('?');
+            for (i, (k, v)) in qs.pairs().enumerate() {
+                if i > 0 {
+                    full.push('&');
+                }
+                full.push_str(k);
+                full.push('=');
+                full.push_str(v);
+            }
+            tail.push_str("?…");
+        }
+        if let Some(fragment) = self.fragment.as_ref() {
+            full.push('#');
+            full.push_str(fragment);
+            tail.push_str("#…");
+        }
+        if fits(&full) {
+            return full;
+        }
+
+        // keep as many leading segments as fit in front of the last one
+        let mut shortened = None;
+        for kept in (0..segments.len()).rev() {
+            let elided = kept + 1 < segments.len();
+            let candidate = format!(
+                "{}{}{}",
+                head,
+                path(&segments[..kept + usize::from(!elided)], elided),
+                tail
+            );
+            if fits(&candidate) {
+                shortened = Some(candidate);
+                break;
+            }
+        }
+        if let Some(shortened) = shortened {
+            return shortened;
+        }
+        if segments.is_empty() && fits(&format!("{}{}", head, tail)) {
+            return format!("{}{}", head, tail);
+        }
+        match max_len {
+            0 => String::new(),
+            _ => {
+                let mut cut: String = full.chars().take(max_len - 1).collect();
+                cut.push('…');
+                cut
+            }
+        }
+    }
+
+    /// Write each component on its own line, for `{:#}`
+    ///
+    /// Userinfo is left out so passwords don't end up in logs, and query
+    /// pairs are sorted by key.
+    pub(crate) fn fmt_tree(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "scheme: {}", self.scheme)?;
+        if self.path_kind == PathKind::AfterAuthority {
+            writeln!(f, "host: {}", self.authority.host)?;
+        }
+        if let Some(port) = self.authority.port {
+            writeln!(f, "port: {}", port)?;
+        }
+        if let Some(path) = self.path.as_ref().filter(|p| !p.is_empty()) {
+            writeln!(f, "path:")?;
+            for segment in path {
+                writeln!(f, "  {}", segment)?;
+            }
+        }
+        if let Some(qs) = self.qs.as_ref() {
+            let mut pairs: Vec<_> = qs.pairs().collect();
+            pairs.sort();
+            writeln!(f, "query:")?;
+            for (k, v) in pairs {
+                writeln!(f, "  {} = {}", k, v)?;
+            }
+        }
+        if let Some(fragment) = self.fragment.as_ref() {
+            writeln!(f, "fragment: {}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_serialized_len_and_write_to() {
+        for input in &[
+            "http://h",
+            "http://u:p@h:8080/a//b/?k=v&x=#f",
+            "ftp://u@[::1]:1/",
+            "urn:isbn:123",
+            "mailto:",
+            "file:/etc/hosts?",
+            "s://h:0?",
+        ] {
+            let uri = input.parse::<URI<String>>().unwrap();
+            assert_eq!(uri.serialized_len(), uri.to_string().len(), "{}", input);
+            let mut written = Vec::new();
+            uri.write_to(&mut written).unwrap();
+            assert_eq!(written, uri.to_string().as_bytes(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_vendor_schemes_round_trip() {
+        for input in &[
+            "spotify:track:6rqhFgbbKwnb9MLmUQDhG6",
+            "steam://run/440",
+            "vscode://file/home/a.rs:10:2",
+            "about:blank",
+        ] {
+            let uri = input.parse::<URI<String>>().unwrap();
+            assert_eq!(uri.to_string(), *input);
+        }
+    }
+
+    #[test]
+    fn test_tree_leaves_out_userinfo() {
+        let uri = "ftp://user:pw@h?b=2&a=1".parse::<URI<String>>().unwrap();
+        assert_eq!(
+            format!("{:#}", uri),
+            "scheme: ftp\nhost: h\nquery:\n  a = 1\n  b = 2\n"
+        );
+    }
+
+    #[test]
+    fn test_display_truncated() {
+        let uri = "https://u:pw@example.com/aa/bb/cc/zz?k=v#frag"
+            .parse::<URI<String>>()
+            .unwrap();
+        assert_eq!(
+            uri.display_truncated(100),
+            "https://example.com/aa/bb/cc/zz?k=v#frag"
+        );
+        assert_eq!(
+            uri.display_truncated(39),
+            "https://example.com/aa/bb/cc/zz?…#…"
+        );
+        assert_eq!(
+            uri.display_truncated(34),
+            "https://example.com/aa/bb/…/zz?…#…"
+        );
+        assert_eq!(uri.display_truncated(30), "https://example.com/…/zz?…#…");
+        assert_eq!(uri.display_truncated(10), "https://e…");
+        assert_eq!(uri.display_truncated(0), "");
+        for max_len in 0..50 {
+            assert!(uri.display_truncated(max_len).chars().count() <= max_len);
+        }
+        let uri = "urn:isbn:0451450523".parse::<URI<String>>().unwrap();
+        assert_eq!(uri.display_truncated(12), "urn:isbn:04…");
+    }
+
+    #[test]
+    fn test_space_encoding() {
+        let mut uri = "http://h/a?a%20b=x".parse::<URI<String>>().unwrap();
+        uri.qs
+            .as_mut()
+            .unwrap()
+            .insert("a%20b".to_string(), "c+d e".to_string());
+        let plus = DisplayOptions::default().space_encoding(SpaceEncoding::Plus);
+        assert_eq!(uri.to_string_with(&plus), "http://h/a?a+b=c+d+e");
+        let percent = DisplayOptions::default().space_encoding(SpaceEncoding::Percent);
+        assert_eq!(uri.to_string_with(&percent), "http://h/a?a%20b=c+d%20e");
+        assert_eq!(
+            uri.to_string_with(&DisplayOptions::default()),
+            uri.to_string()
+        );
+    }
+
+    #[test]
+    fn test_serialization_policy() {
+        let with = |s: &str, options: &DisplayOptions| {
+            s.parse::<URI<String>>().unwrap().to_string_with(options)
+        };
+        let ports = DisplayOptions::default().omit_default_port(true);
+        assert_eq!(with("http://h:80/a", &ports), "http://h/a");
+        assert_eq!(with("HTTPS://h:443", &ports), "HTTPS://h");
+        assert_eq!(with("http://h:8080/a", &ports), "http://h:8080/a");
+        assert_eq!(
+            with("http://h:443/a", &DisplayOptions::default()),
+            "http://h:443/a"
+        );
+
+        let force = DisplayOptions::default().trailing_slash(TrailingSlash::Force);
+        assert_eq!(with("http://h", &force), "http://h/");
+        assert_eq!(with("http://h/a?k=v", &force), "http://h/a/?k=v");
+        assert_eq!(with("http://h/a/", &force), "http://h/a/");
+        assert_eq!(with("mailto:", &force), "mailto:");
+        assert_eq!(with("urn:a:b", &force), "urn:a:b");
+        assert_eq!(with("x:/a", &force), "x:/a/");
+
+        let forbid = DisplayOptions::default().trailing_slash(TrailingSlash::Forbid);
+        assert_eq!(with("http://h/", &forbid), "http://h");
+        assert_eq!(with("http://h/a/#f", &forbid), "http://h/a#f");
+        assert_eq!(with("http://h/a", &forbid), "http://h/a");
+        assert_eq!(with("x:/", &forbid), "x:/");
+        assert_eq!(with("x:/a/", &forbid), "x:/a");
+        assert_eq!(with("urn:a:b/", &forbid), "urn:a:b/");
+
+        let no_empty = DisplayOptions::default().empty_query(false);
+        assert_eq!(with("http://h/?#f", &no_empty), "http://h/#f");
+        assert_eq!(with("http://h/?", &DisplayOptions::default()), "http://h/?");
+
+        let sorted = DisplayOptions::default().sort_params(true);
+        assert_eq!(
+            with("http://h/?c=3&a=1&b=2", &sorted),
+            "http://h/?a=1&b=2&c=3"
+        );
+    }
+
+    #[test]
+    fn test_btree_query_is_ordered() {
+        use std::collections::BTreeMap;
+
+        let uri: URI<String, BTreeMap<String, String>> = "http://h/?c=3&a=1&b=2".parse().unwrap();
+        assert_eq!(uri.to_string(), "http://h/?a=1&b=2&c=3");
+        let reordered: URI<String, BTreeMap<String, String>> =
+            "http://h/?b=2&c=3&a=1".parse().unwrap();
+        assert_eq!(uri, reordered);
+    }
+}
@@ -0,0 +1,132 @@
+//! Finding URIs in free text
+//!
+//! # Examples
+//!
+//! ```
+//! let text = "See https://example.com/docs, or (mirror: ftp://files.example.com/pub).";
+//! let found: Vec<_> = auris::extract(text)
+//!     .map(|(span, uri)| (&text[span], uri.to_string()))
+//!     .collect();
+//!
+//! assert_eq!(
+//!     found,
+//!     vec![
+//!         ("https://example.com/docs", "https://example.com/docs".to_string()),
+//!         ("ftp://files.example.com/pub", "ftp://files.example.com/pub".to_string()),
+//!     ]
+//! );
+//! ```
+use crate::{parsers, URI};
+use std::ops::Range;
+
+/// Characters that never appear in a URI written out in prose
+fn is_boundary(c: char) -> bool {
+    c.is_whitespace() || c == '<' || c == '>' || c == '"' || c == '`'
+}
+
+/// Trim punctuation that more likely ends the sentence than the URI
+fn trim_trailing(candidate: &str) -> &str {
+    let mut candidate = candidate;
+    loop {
+        let unmatched = |open: char, close: char| {
+            candidate.matches(close).count() > candidate.matches(open).count()
+        };
+        let trimmed = match candidate.chars().last() {
+            Some('.' | ',' | ';' | ':' | '!' | '?' | '\'' | '*') => true,
+            Some(')') => unmatched('(', ')'),
+            Some(']') => unmatched('[', ']'),
+            Some('}') => unmatched('{', '}'),
+            _ => false,
+        };
+        if !trimmed {
+            return candidate;
+        }
+        candidate = &candidate[..candidate.len() - 1];
+    }
+}
+
+/// Iterator over the URIs in a piece of text, see [`extract`]
+#[derive(Debug, Clone)]
+pub struct Extract<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+/// Find the URIs in prose, logs or markdown
+///
+/// Yields the byte range of each URI in `text` along with the parsed URI.
+/// Only URIs with an authority (`scheme://...`) are found. Trailing
+/// punctuation and closing brackets without a matching opening bracket are
+/// not considered part of the URI.
+pub fn extract(text: &str) -> Extract<'_> {
+    Extract { text, pos: 0 }
+}
+
+impl Iterator for Extract<'_> {
+    type Item = (Range<usize>, URI<String>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(found) = self.text[self.pos..].find("://") {
+            let separator = self.pos + found;
+            let resume = separator + "://".len();
+
+            // walk back over the scheme, which has to start with a letter
+            let before = &self.text[self.pos..separator];
+            let scheme_len = before
+                .chars()
+                .rev()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '+' || *c == '-' || *c == '.')
+                .count();
+            let scheme = &before[before.len() - scheme_len..];
+            let scheme = scheme.trim_start_matches(|c: char| !c.is_ascii_alphabetic());
+            if scheme.is_empty() {
+                self.pos = resume;
+                continue;
+            }
+            let start = separator - scheme.len();
+
+            let end = self.text[resume..]
+                .find(is_boundary)
+                .map_or(self.text.len(), |e| resume + e);
+            let candidate = trim_trailing(&self.text[start..end]);
+
+            match parsers::uri(candidate) {
+                Ok((remaining, uri)) if !uri.authority.host.is_empty() => {
+                    let end = start + candidate.len() - remaining.len();
+                    self.pos = end;
+                    return Some((start..end, uri.to_owned()));
+                }
+                _ => self.pos = resume,
+            }
+        }
+        self.pos = self.text.len();
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn spans(text: &str) -> Vec<&str> {
+        extract(text).map(|(span, _)| &text[span]).collect()
+    }
+
+    #[test]
+    fn test_extract_boundaries() {
+        assert_eq!(
+            spans("[docs](https://example.com/a_(b)) and <http://example.org/>!"),
+            vec!["https://example.com/a_(b)", "http://example.org/"]
+        );
+        assert_eq!(
+            spans("\"git+ssh://git@example.com/repo.git\"; 2020-01-01 GET http://[::1]:8080/health?x=1."),
+            vec!["git+ssh://git@example.com/repo.git", "http://[::1]:8080/health?x=1"]
+        );
+    }
+
+    #[test]
+    fn test_extract_skips_non_uris() {
+        assert!(spans("nothing here :// at all, not even ://").is_empty());
+        assert_eq!(spans("1://nope x://"), Vec::<&str>::new());
+    }
+}
@@ -0,0 +1,240 @@
+//! Fragment directives, such as scroll-to-text `#:~:text=` links
+//!
+//! Everything after `:~:` in the fragment is a directive for the browser
+//! rather than part of the fragment the page sees.
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::URI;
+//!
+//! let uri = "https://example.com/#intro:~:text=an%20example-,start,end,-of%2C%20it"
+//!     .parse::<URI<String>>()
+//!     .unwrap();
+//! let text = &uri.text_directives()[0];
+//!
+//! assert_eq!(uri.plain_fragment(), Some("intro"));
+//! assert_eq!(text.prefix.as_deref(), Some("an example"));
+//! assert_eq!(text.start, "start");
+//! assert_eq!(text.end.as_deref(), Some("end"));
+//! assert_eq!(text.suffix.as_deref(), Some("of, it"));
+//! ```
+use crate::{
+    parsers, percent, AurisParseErrorKind, ParseError, ParseOptions, QueryMap, QueryStore, URI,
+};
+use nom::combinator::all_consuming;
+use std::str::FromStr;
+
+/// Separates the fragment from the fragment directive
+const DIRECTIVE_DELIMITER: &str = ":~:";
+
+/// Split a fragment into the plain fragment and the fragment directive
+///
+/// # Examples
+///
+/// ```
+/// use auris::fragment;
+///
+/// assert_eq!(fragment::split_directive("top:~:text=a"), ("top", Some("text=a")));
+/// assert_eq!(fragment::split_directive("top"), ("top", None));
+/// ```
+pub fn split_directive(fragment: &str) -> (&str, Option<&str>) {
+    match fragment.split_once(DIRECTIVE_DELIMITER) {
+        Some((plain, directive)) => (plain, Some(directive)),
+        None => (fragment, None),
+    }
+}
+
+/// A `text=[prefix-,]start[,end][,-suffix]` directive, with its terms
+/// percent decoded
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TextDirective {
+    pub prefix: Option<String>,
+    pub start: String,
+    pub end: Option<String>,
+    pub suffix: Option<String>,
+}
+
+/// Parses the value of a `text=` directive
+impl FromStr for TextDirective {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut terms: Vec<&str> = s.split(',').collect();
+        let prefix = match terms.first() {
+            Some(first) if first.ends_with('-') => {
+                let prefix = &first[..first.len() - 1];
+                terms.remove(0);
+                Some(prefix)
+            }
+            _ => None,
+        };
+        let suffix = match terms.last() {
+            Some(last) if last.starts_with('-') => {
+                let suffix = &last[1..];
+                terms.pop();
+                Some(suffix)
+            }
+            _ => None,
+        };
+        let (start, end) = match terms[..] {
+            [start] => (start, None),
+            [start, end] => (start, Some(end)),
+            _ => return Err(AurisParseErrorKind::Failed.into()),
+        };
+        let terms = [prefix, Some(start), end, suffix];
+        if terms.iter().flatten().any(|t| t.is_empty()) {
+            return Err(AurisParseErrorKind::Failed.into());
+        }
+        let decode = |t: Option<&str>| t.map(|t| percent::decode(t).into_owned());
+        Ok(TextDirective {
+            prefix: decode(prefix),
+            start: percent::decode(start).into_owned(),
+            end: decode(end),
+            suffix: decode(suffix),
+        })
+    }
+}
+
+impl URI<String> {
+    /// The fragment without any fragment directive
+    pub fn plain_fragment(&self) -> Option<&str> {
+        self.fragment.as_deref().map(|f| split_directive(f).0)
+    }
+
+    /// The fragment directive, after the `:~:`
+    pub fn fragment_directive(&self) -> Option<&str> {
+        self.fragment.as_deref().and_then(|f| split_directive(f).1)
+    }
+
+    /// The `text=` directives in the fragment directive, in order
+    ///
+    /// Malformed directives are skipped, as browsers do.
+    pub fn text_directives(&self) -> Vec<TextDirective> {
+        self.fragment_directive()
+            .into_iter()
+            .flat_map(|d| d.split('&'))
+            .filter_map(|d| d.strip_prefix("text="))
+            .filter_map(|text| text.parse().ok())
+            .collect()
+    }
+
+    /// The fragment parsed as `k=v&k1=v1` pairs, as OAuth implicit flows
+    /// return `#access_token=…&state=…`
+    ///
+    /// Repeated keys are handled as when parsing the query with the default
+    /// [`ParseOptions`]. A URI without a fragment has no params.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "https://app.example/cb#access_token=x1y2".parse::<URI<String>>().unwrap();
+    /// let params = uri.fragment_params().unwrap();
+    /// assert_eq!(params.get("access_token").map(String::as_str), Some("x1y2"));
+    /// ```
+    pub fn fragment_params(&self) -> Result<QueryMap<String, String>, ParseError> {
+        self.fragment_params_with(&ParseOptions::default())
+    }
+
+    /// The fragment parsed as `k=v&k1=v1` pairs into any [`QueryStore`],
+    /// with repeated keys handled as `options` handles them in the query
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::{DuplicateKeys, ParseOptions, QueryMap, QueryPairs, URI};
+    ///
+    /// let uri = "https://app.example/cb#state=a&code=x&state=b".parse::<URI<String>>().unwrap();
+    /// let options = ParseOptions::default().duplicate_keys(DuplicateKeys::Error);
+    /// assert!(uri.fragment_params_with::<QueryMap<_, _>>(&options).is_err());
+    ///
+    /// let options = ParseOptions::default().duplicate_keys(DuplicateKeys::CollectAll);
+    /// let pairs: QueryPairs<String, String> = uri.fragment_params_with(&options).unwrap();
+    /// assert_eq!(pairs[1], ("code".to_string(), "x".to_string()));
+    /// ```
+    pub fn fragment_params_with<Q: QueryStore>(
+        &self,
+        options: &ParseOptions,
+    ) -> Result<Q, ParseError> {
+        let fragment = match self.fragment.as_deref() {
+            Some(fragment) => format!("?{}", fragment),
+            None => return Ok(Q::default()),
+        };
+        let pairs = match all_consuming(parsers::query_pairs)(&fragment) {
+            Ok((_, pairs)) => pairs,
+            Err(e) => return Err(ParseError::from_nom(e)),
+        };
+        options.collect_query(pairs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_text_directive_terms() {
+        let text = |s: &str| s.parse::<TextDirective>();
+        assert_eq!(
+            text("a-,b").unwrap(),
+            TextDirective {
+                prefix: Some("a".to_string()),
+                start: "b".to_string(),
+                end: None,
+                suffix: None,
+            }
+        );
+        assert_eq!(text("b,-c").unwrap().suffix.as_deref(), Some("c"));
+        assert_eq!(text("%2D%2C").unwrap().start, "-,");
+        assert!(text("a,b,c").is_err());
+        assert!(text("-,b").is_err());
+        assert!(text("a-,-b").is_err());
+        assert!(text("").is_err());
+    }
+
+    #[test]
+    fn test_several_directives() {
+        let uri = "http://h/#:~:text=a&unknown=1&text=b,c&text=,"
+            .parse::<URI<String>>()
+            .unwrap();
+        let starts: Vec<_> = uri.text_directives().into_iter().map(|t| t.start).collect();
+        assert_eq!(starts, vec!["a", "b"]);
+        assert_eq!(uri.plain_fragment(), Some(""));
+        assert_eq!(
+            uri.fragment_directive(),
+            Some("text=a&unknown=1&text=b,c&text=,")
+        );
+    }
+
+    #[test]
+    fn test_fragment_params() {
+        let params = |s: &str| s.parse::<URI<String>>().unwrap().fragment_params();
+        let oauth = params("https://a.example/cb#access_token=t&token_type=bearer&state=s&state=z")
+            .unwrap();
+        assert_eq!(oauth.len(), 3);
+        assert_eq!(oauth.get("token_type").map(String::as_str), Some("bearer"));
+        assert_eq!(oauth.get("state").map(String::as_str), Some("z"));
+        assert!(params("http://h/").unwrap().is_empty());
+        assert!(params("http://h/#").unwrap().is_empty());
+        assert_eq!(
+            params("http://h/#flag")
+                .unwrap()
+                .get("flag")
+                .map(String::as_str),
+            Some("")
+        );
+        assert_eq!(params("http://h/#a&&b").unwrap().len(), 2);
+
+        let uri = "http://h/#k=1&k=2".parse::<URI<String>>().unwrap();
+        let first = ParseOptions::default().duplicate_keys(crate::DuplicateKeys::FirstWins);
+        let params: QueryMap<String, String> = uri.fragment_params_with(&first).unwrap();
+        assert_eq!(params.get("k").map(String::as_str), Some("1"));
+
+        let all = ParseOptions::default().duplicate_keys(crate::DuplicateKeys::CollectAll);
+        let pairs: crate::QueryPairs<String, String> = uri.fragment_params_with(&all).unwrap();
+        let values: Vec<_> = pairs.iter().map(|(_, v)| v.as_str()).collect();
+        assert_eq!(values, ["1", "2"]);
+    }
+}
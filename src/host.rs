@@ -0,0 +1,526 @@
+//! Typed hosts, and matching them against patterns and networks
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::{Host, URI};
+//! use auris::host::HostPattern;
+//!
+//! let host = "[0:0::1]".parse::<Host>().unwrap();
+//! assert_eq!(host, Host::Ipv6("::1".parse().unwrap()));
+//! assert_eq!(host.to_string(), "[::1]");
+//!
+//! let pattern = "*.example.com".parse::<HostPattern>().unwrap();
+//! let uri = "https://api.example.com/v1".parse::<URI<String>>().unwrap();
+//!
+//! assert!(pattern.matches_uri(&uri));
+//! assert!(!pattern.matches("evilexample.com"));
+//! ```
+use crate::proxy::{cidr_contains, parse_cidr};
+use crate::{AurisParseErrorKind, Host, ParseError, URI};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/// Parses a host as written in an authority
+///
+/// Domains keep their case, escapes in them are decoded. An IPv6 address
+/// without brackets fails with [`AurisParseErrorKind::UnbracketedIpv6`].
+impl FromStr for Host {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.parse::<Ipv6Addr>().is_ok() {
+            return Err(AurisParseErrorKind::UnbracketedIpv6.into());
+        }
+        Host::from_authority_host(s).ok_or_else(|| AurisParseErrorKind::Failed.into())
+    }
+}
+
+/// Writes the host as it goes in an authority, IP addresses in their
+/// canonical form
+impl<S: fmt::Display> fmt::Display for Host<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Host::Domain(domain) => write!(f, "{}", domain),
+            Host::Ipv4(ip) => write!(f, "{}", ip),
+            Host::Ipv6(ip) => write!(f, "[{}]", ip),
+        }
+    }
+}
+
+/// How internationalized domain names are converted, following UTS #46
+///
+/// The defaults are those of the WHATWG URL standard: nontransitional
+/// processing and no STD3 rules, with invalid Punycode an error. Requires
+/// the `idna` feature.
+///
+/// # Examples
+///
+/// ```
+/// use auris::host::IdnaOptions;
+///
+/// let options = IdnaOptions::default();
+/// assert_eq!(options.to_ascii("Bücher.example").unwrap(), "xn--bcher-kva.example");
+/// assert_eq!(options.to_unicode("xn--bcher-kva.example").unwrap(), "bücher.example");
+///
+/// // IDNA 2003 maps ß to ss, IDNA 2008 keeps it
+/// assert_eq!(options.to_ascii("faß.de").unwrap(), "xn--fa-hia.de");
+/// assert_eq!(options.transitional(true).to_ascii("faß.de").unwrap(), "fass.de");
+/// ```
+#[cfg(feature = "idna")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IdnaOptions {
+    transitional: bool,
+    std3_rules: bool,
+    verify_dns_length: bool,
+    keep_invalid_punycode: bool,
+}
+
+#[cfg(feature = "idna")]
+impl IdnaOptions {
+    /// Map deviation characters such as `ß` and `ς` as IDNA 2003 did,
+    /// rather than keeping them as IDNA 2008 does
+    pub fn transitional(mut self, transitional: bool) -> Self {
+        self.transitional = transitional;
+        self
+    }
+
+    /// Only allow letters, digits and hyphens, as DNS host names require,
+    /// rather than any ASCII such as `_`
+    pub fn std3_rules(mut self, std3_rules: bool) -> Self {
+        self.std3_rules = std3_rules;
+        self
+    }
+
+    /// Reject ASCII results with labels over 63 bytes or names over 253
+    pub fn verify_dns_length(mut self, verify: bool) -> Self {
+        self.verify_dns_length = verify;
+        self
+    }
+
+    /// Keep `xn--` labels whose Punycode is malformed or overflows as they
+    /// are, instead of failing the whole domain
+    pub fn keep_invalid_punycode(mut self, keep: bool) -> Self {
+        self.keep_invalid_punycode = keep;
+        self
+    }
+
+    fn config(&self) -> idna::Config {
+        idna::Config::default()
+            .transitional_processing(self.transitional)
+            .use_std3_ascii_rules(self.std3_rules)
+            .verify_dns_length(self.verify_dns_length)
+    }
+
+    /// Whether `label` is an `xn--` label that doesn't decode
+    fn is_invalid_punycode(label: &str) -> bool {
+        match label.get(..4) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("xn--") && label.len() > 4 => {
+                idna::punycode::decode(&label[4..]).is_none()
+            }
+            _ => false,
+        }
+    }
+
+    /// Convert each label on its own, so invalid Punycode can be kept
+    fn convert_labels<F>(&self, domain: &str, convert: F) -> Result<String, ParseError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let failed = || ParseError::from(AurisParseErrorKind::Rejected("invalid domain name"));
+        if !self.keep_invalid_punycode || !domain.split('.').any(Self::is_invalid_punycode) {
+            return convert(domain).ok_or_else(failed);
+        }
+        let labels = domain
+            .split('.')
+            .map(|label| {
+                if Self::is_invalid_punycode(label) {
+                    Some(label.to_ascii_lowercase())
+                } else {
+                    convert(label)
+                }
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(failed)?;
+        Ok(labels.join("."))
+    }
+
+    /// The ASCII form of `domain`, with non-ASCII labels Punycode encoded
+    pub fn to_ascii(&self, domain: &str) -> Result<String, ParseError> {
+        self.convert_labels(domain, |d| self.config().to_ascii(d).ok())
+    }
+
+    /// The Unicode form of `domain`, with `xn--` labels decoded
+    pub fn to_unicode(&self, domain: &str) -> Result<String, ParseError> {
+        self.convert_labels(domain, |d| match self.config().to_unicode(d) {
+            (unicode, Ok(())) => Some(unicode),
+            (_, Err(_)) => None,
+        })
+    }
+}
+
+#[cfg(feature = "idna")]
+impl URI<String> {
+    /// Convert the host to its ASCII form, as needed for DNS lookups
+    ///
+    /// Escapes in the host are decoded first. IP hosts are left as they
+    /// are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    /// use auris::host::IdnaOptions;
+    ///
+    /// let mut uri = "http://%E4%BE%8B%E3%81%88.jp/".parse::<URI<String>>().unwrap();
+    /// uri.host_to_ascii(&IdnaOptions::default()).unwrap();
+    ///
+    /// assert_eq!("http://xn--r8jz45g.jp/", format!("{}", uri));
+    /// ```
+    pub fn host_to_ascii(&mut self, options: &IdnaOptions) -> Result<(), ParseError> {
+        self.convert_host(|host| options.to_ascii(host))
+    }
+
+    /// Convert the host to its Unicode form, for display
+    ///
+    /// The result may contain non-ASCII characters, which a URI can't.
+    pub fn host_to_unicode(&mut self, options: &IdnaOptions) -> Result<(), ParseError> {
+        self.convert_host(|host| options.to_unicode(host))
+    }
+
+    fn convert_host<F>(&mut self, convert: F) -> Result<(), ParseError>
+    where
+        F: Fn(&str) -> Result<String, ParseError>,
+    {
+        let host = &self.authority.host;
+        if host.is_empty() || host.starts_with('[') || host.parse::<std::net::Ipv4Addr>().is_ok() {
+            return Ok(());
+        }
+        self.authority.host = convert(&crate::percent::decode(host))?;
+        Ok(())
+    }
+}
+
+/// A number in an IPv4 host: hex after `0x`, octal after a leading `0`,
+/// otherwise decimal
+fn ipv4_number(part: &str) -> Option<u64> {
+    let (digits, radix) = match part.get(..2) {
+        Some("0x") | Some("0X") => (&part[2..], 16),
+        _ if part.len() > 1 && part.starts_with('0') => (&part[1..], 8),
+        _ => (part, 10),
+    };
+    if digits.is_empty() {
+        // `0x` alone is zero
+        return Some(0).filter(|_| radix == 16);
+    }
+    if !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    // anything longer overflows any IPv4 address anyway
+    u64::from_str_radix(digits, radix).ok().or(Some(u64::MAX))
+}
+
+/// Read a host the way the WHATWG URL standard's IPv4 parser does, which
+/// accepts one to four numbers in decimal, octal or hex
+///
+/// Hosts not ending in a number are domains, `Ok(None)`. Hosts ending in
+/// a number that isn't a valid address are an error.
+pub(crate) fn parse_numeric_ipv4(host: &str) -> Result<Option<Ipv4Addr>, ParseError> {
+    let mut parts: Vec<&str> = host.split('.').collect();
+    if parts.len() > 1 && parts.last() == Some(&"") {
+        parts.pop();
+    }
+    match parts.last() {
+        Some(last) if ipv4_number(last).is_some() => (),
+        _ => return Ok(None),
+    }
+    let invalid = || ParseError::from(AurisParseErrorKind::Rejected("invalid IPv4 address"));
+    let numbers = parts
+        .iter()
+        .map(|part| ipv4_number(part))
+        .collect::<Option<Vec<u64>>>()
+        .filter(|numbers| numbers.len() <= 4)
+        .ok_or_else(invalid)?;
+    let (last, leading) = numbers.split_last().ok_or_else(invalid)?;
+    if leading.iter().any(|n| *n > 255) || *last >= 1 << (8 * (5 - numbers.len())) {
+        return Err(invalid());
+    }
+    let address = leading
+        .iter()
+        .enumerate()
+        .fold(*last, |address, (i, n)| address + (n << (8 * (3 - i))));
+    Ok(Some(Ipv4Addr::from(address as u32)))
+}
+
+impl<S> Host<S> {
+    /// Whether the host is an IP address within the network `cidr`, written
+    /// as `10.0.0.0/8` or `fd00::/8`
+    ///
+    /// Domains never match, and IPv4 addresses never match IPv6 networks.
+    /// IPv4-mapped IPv6 addresses, such as `[::ffff:10.0.0.1]`, match IPv4
+    /// networks as the address they map, as well as IPv6 networks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::{Authority, Host};
+    ///
+    /// let (host, _) = Authority::parse_host_header("10.1.2.3:8080").unwrap();
+    /// assert!(host.matches_cidr("10.0.0.0/8").unwrap());
+    /// assert!(!host.matches_cidr("192.168.0.0/16").unwrap());
+    ///
+    /// let (host, _) = Authority::parse_host_header("[fd00::1]").unwrap();
+    /// assert!(host.matches_cidr("fd00::/8").unwrap());
+    ///
+    /// let (host, _) = Authority::parse_host_header("[::ffff:10.0.0.1]").unwrap();
+    /// assert!(host.matches_cidr("10.0.0.0/8").unwrap());
+    ///
+    /// assert!(host.matches_cidr("10.0.0.0/33").is_err());
+    /// ```
+    pub fn matches_cidr(&self, cidr: &str) -> Result<bool, ParseError> {
+        let (net, prefix) = parse_cidr(cidr)?;
+        let ip = match self {
+            Host::Ipv4(ip) => IpAddr::V4(*ip),
+            Host::Ipv6(ip) => match (ip.to_ipv4_mapped(), net) {
+                (Some(mapped), IpAddr::V4(_)) => IpAddr::V4(mapped),
+                _ => IpAddr::V6(*ip),
+            },
+            Host::Domain(_) => return Ok(false),
+        };
+        Ok(cidr_contains(net, prefix, ip))
+    }
+}
+
+impl<S: AsRef<str>> Host<S> {
+    /// Whether both hosts are the same, comparing domains ignoring ASCII
+    /// case and IP addresses by value
+    ///
+    /// An IPv4 address never equals an IPv6 one, IPv4-mapped or not.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::Host;
+    ///
+    /// let host = |s: &str| s.parse::<Host>().unwrap();
+    /// assert!(host("Example.COM").eq_ignore_case(&host("example.com")));
+    /// assert!(host("[2001:DB8::1]").eq_ignore_case(&host("[2001:db8:0::1]")));
+    /// assert!(!host("[::ffff:127.0.0.1]").eq_ignore_case(&host("127.0.0.1")));
+    /// ```
+    pub fn eq_ignore_case<T: AsRef<str>>(&self, other: &Host<T>) -> bool {
+        match (self, other) {
+            (Host::Domain(a), Host::Domain(b)) => a.as_ref().eq_ignore_ascii_case(b.as_ref()),
+            (Host::Ipv4(a), Host::Ipv4(b)) => a == b,
+            (Host::Ipv6(a), Host::Ipv6(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// A host, or a wildcard standing for one label in front of a domain
+///
+/// Matching follows the certificate rules of RFC 6125 section 6.4.3: the
+/// wildcard is the whole leftmost label and matches exactly one label, so
+/// `*.example.com` matches `www.example.com` but neither `example.com` nor
+/// `a.b.example.com`. Domains are compared case insensitively and ignoring
+/// a trailing dot, IP addresses by value. Wildcards never match IP
+/// addresses.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum HostPattern {
+    Exact(Host),
+    /// `*.` followed by the lowercased domain
+    Wildcard(String),
+}
+
+/// Lowercase a domain and drop a trailing dot
+fn normalize_domain(domain: &str) -> String {
+    domain
+        .strip_suffix('.')
+        .unwrap_or(domain)
+        .to_ascii_lowercase()
+}
+
+impl FromStr for HostPattern {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rejected = |reason| ParseError::from(AurisParseErrorKind::Rejected(reason));
+        if let Some(domain) = s.strip_prefix("*.") {
+            return match Host::from_authority_host(domain) {
+                Some(Host::Domain(domain)) if !domain.contains('*') => {
+                    Ok(HostPattern::Wildcard(normalize_domain(&domain)))
+                }
+                _ => Err(rejected("wildcard must be followed by a domain")),
+            };
+        }
+        if s.contains('*') {
+            return Err(rejected("wildcard must be the whole leftmost label"));
+        }
+        match Host::from_authority_host(s) {
+            Some(Host::Domain(domain)) => {
+                Ok(HostPattern::Exact(Host::Domain(normalize_domain(&domain))))
+            }
+            Some(host) => Ok(HostPattern::Exact(host)),
+            None => Err(rejected("invalid host")),
+        }
+    }
+}
+
+impl HostPattern {
+    /// Whether `host`, as written in an authority, matches the pattern
+    pub fn matches(&self, host: &str) -> bool {
+        let host = match Host::from_authority_host(host) {
+            Some(Host::Domain(domain)) => Host::Domain(normalize_domain(&domain)),
+            Some(host) => host,
+            None => return false,
+        };
+        match (self, host) {
+            (HostPattern::Exact(pattern), host) => *pattern == host,
+            (HostPattern::Wildcard(suffix), Host::Domain(domain)) => domain
+                .split_once('.')
+                .is_some_and(|(label, rest)| !label.is_empty() && rest == suffix),
+            (HostPattern::Wildcard(_), _) => false,
+        }
+    }
+
+    /// Whether the host of `uri` matches the pattern
+    pub fn matches_uri(&self, uri: &URI<String>) -> bool {
+        uri.has_authority() && self.matches(&uri.authority.host)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn matches(pattern: &str, host: &str) -> bool {
+        pattern.parse::<HostPattern>().unwrap().matches(host)
+    }
+
+    #[test]
+    fn test_wildcard_label_boundaries() {
+        assert!(matches("*.example.com", "www.example.com"));
+        assert!(matches("*.Example.com.", "WWW.example.COM"));
+        assert!(!matches("*.example.com", "example.com"));
+        assert!(!matches("*.example.com", "a.b.example.com"));
+        assert!(!matches("*.example.com", "wwwexample.com"));
+        assert!(!matches("*.example.com", "www.example.com.evil"));
+    }
+
+    #[test]
+    fn test_exact() {
+        assert!(matches("example.com", "EXAMPLE.com."));
+        assert!(!matches("example.com", "www.example.com"));
+        assert!(!matches("example.com", "notexample.com"));
+        assert!(matches("[::1]", "[0::1]"));
+        assert!(matches("127.0.0.1", "127.0.0.1"));
+        assert!(!matches("*.0.0.1", "127.0.0.1"));
+    }
+
+    #[test]
+    fn test_host_from_str_and_display() {
+        let round_trip = |s: &str| s.parse::<Host>().unwrap().to_string();
+        assert_eq!(round_trip("Example.com"), "Example.com");
+        assert_eq!(round_trip("ex%61mple.com"), "example.com");
+        assert_eq!(round_trip("127.0.0.1"), "127.0.0.1");
+        assert_eq!(round_trip("[2001:DB8:0::1]"), "[2001:db8::1]");
+        assert_eq!(
+            "::1".parse::<Host>().unwrap_err().kind(),
+            AurisParseErrorKind::UnbracketedIpv6
+        );
+        for host in &["", "a/b", "[::1", "a b"] {
+            assert!(host.parse::<Host>().is_err(), "{}", host);
+        }
+
+        let hosts: std::collections::HashSet<Host> = ["[::1]", "[0::1]", "h"]
+            .iter()
+            .map(|h| h.parse().unwrap())
+            .collect();
+        assert_eq!(hosts.len(), 2);
+    }
+
+    #[test]
+    fn test_matches_cidr() {
+        let host = |s: &str| Host::from_authority_host(s).unwrap();
+        assert!(host("0.0.0.0").matches_cidr("0.0.0.0/0").unwrap());
+        assert!(host("192.168.1.1").matches_cidr("192.168.1.1/32").unwrap());
+        assert!(!host("192.168.1.2").matches_cidr("192.168.1.1/32").unwrap());
+        let mapped = host("[::ffff:10.0.0.1]");
+        assert!(mapped.matches_cidr("10.0.0.0/8").unwrap());
+        assert!(!mapped.matches_cidr("192.168.0.0/16").unwrap());
+        assert!(mapped.matches_cidr("::ffff:0:0/96").unwrap());
+        assert!(!host("[::10.0.0.1]").matches_cidr("10.0.0.0/8").unwrap());
+        assert!(host("[2001:db8::1]").matches_cidr("2001:db8::/32").unwrap());
+        assert!(!host("example.com").matches_cidr("0.0.0.0/0").unwrap());
+        assert!(host("example.com").matches_cidr("10.0.0.0").is_err());
+    }
+
+    #[cfg(feature = "idna")]
+    #[test]
+    fn test_idna_options() {
+        let options = IdnaOptions::default();
+        assert!(options.to_ascii("a_b.example").is_ok());
+        assert!(options.std3_rules(true).to_ascii("a_b.example").is_err());
+        let long = "a".repeat(64);
+        assert!(options.to_ascii(&long).is_ok());
+        assert!(options.verify_dns_length(true).to_ascii(&long).is_err());
+
+        // decodes past the largest code point
+        let overflow = "xn--99999999999999999999.example";
+        assert!(options.to_unicode(overflow).is_err());
+        assert_eq!(
+            options
+                .keep_invalid_punycode(true)
+                .to_unicode("XN--99999999999999999999.xn--bcher-kva")
+                .unwrap(),
+            "xn--99999999999999999999.bücher"
+        );
+        // a multibyte character straddling the `xn--` prefix
+        assert!(options
+            .keep_invalid_punycode(true)
+            .to_ascii("abcé.example")
+            .is_ok());
+
+        let mut uri = "http://[::1]/".parse::<URI<String>>().unwrap();
+        uri.host_to_ascii(&options).unwrap();
+        assert_eq!(uri.host_str(), "[::1]");
+        let mut uri = "http://xn--bcher-kva.example/"
+            .parse::<URI<String>>()
+            .unwrap();
+        uri.host_to_unicode(&options).unwrap();
+        assert_eq!(uri.host_str(), "bücher.example");
+    }
+
+    #[test]
+    fn test_numeric_ipv4() {
+        let ipv4 = |s: &str| parse_numeric_ipv4(s).unwrap().map(|ip| ip.to_string());
+        assert_eq!(ipv4("2130706433").unwrap(), "127.0.0.1");
+        assert_eq!(ipv4("0x7f.1").unwrap(), "127.0.0.1");
+        assert_eq!(ipv4("0177.0.0.01").unwrap(), "127.0.0.1");
+        assert_eq!(ipv4("192.168.0x10.").unwrap(), "192.168.0.16");
+        assert_eq!(ipv4("10.0x").unwrap(), "10.0.0.0");
+        assert_eq!(ipv4("0xffffffff").unwrap(), "255.255.255.255");
+        assert_eq!(ipv4("example.com"), None);
+        assert_eq!(ipv4("0x7f.example"), None);
+        assert_eq!(ipv4("1.2.3.0x"), Some("1.2.3.0".to_string()));
+        for host in &[
+            "4294967296",
+            "256.1.1.1",
+            "1.2.3.4.5",
+            "1.2.65536",
+            "09.1",
+            "a.1",
+            "1..1",
+        ] {
+            assert!(parse_numeric_ipv4(host).is_err(), "{}", host);
+        }
+    }
+
+    #[test]
+    fn test_bad_patterns() {
+        for pattern in &["*", "*.", "a.*.com", "w*.example.com", "**.example.com", ""] {
+            assert!(pattern.parse::<HostPattern>().is_err(), "{}", pattern);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Sharing repeated schemes and hosts between parsed URIs
+//!
+//! Crawls and logs see the same few schemes and hosts millions of times.
+//! An [`Interner`] keeps one copy of each and hands out `Arc<str>`s to it,
+//! so a parsed URI only owns its path, query and fragment.
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::intern::Interner;
+//! use std::sync::Arc;
+//!
+//! let mut interner = Interner::default();
+//! let a = interner.parse("https://example.com/a").unwrap();
+//! let b = interner.parse("https://example.com/b?page=2").unwrap();
+//!
+//! assert!(Arc::ptr_eq(&a.authority.host, &b.authority.host));
+//! assert_eq!(interner.len(), 2);
+//! ```
+use crate::{Authority, ParseError, ParseOptions, UserInfo, URI};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Deduplicates the schemes and hosts of the URIs it parses
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    /// The shared copy of `s`, added if it is not there yet
+    pub fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(interned) = self.strings.get(s) {
+            return Arc::clone(interned);
+        }
+        let interned: Arc<str> = Arc::from(s);
+        self.strings.insert(Arc::clone(&interned));
+        interned
+    }
+
+    /// The number of distinct strings interned
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Parse `input` as `FromStr` does, sharing its scheme and host
+    pub fn parse(&mut self, input: &str) -> Result<URI<Arc<str>>, ParseError> {
+        self.parse_with(input, &ParseOptions::default())
+    }
+
+    /// Parse `input` with `options`, sharing its scheme and host
+    pub fn parse_with(
+        &mut self,
+        input: &str,
+        options: &ParseOptions,
+    ) -> Result<URI<Arc<str>>, ParseError> {
+        let uri = URI::parse_with(input, options)?;
+        Ok(URI {
+            scheme: self.intern(&uri.scheme),
+            authority: Authority {
+                host: self.intern(&uri.authority.host),
+                userinfo: uri.authority.userinfo.map(|userinfo| match userinfo {
+                    UserInfo::User(u) => UserInfo::User(Arc::from(u)),
+                    UserInfo::UserAndPassword(u, p) => {
+                        UserInfo::UserAndPassword(Arc::from(u), Arc::from(p))
+                    }
+                }),
+                port: uri.authority.port,
+            },
+            path: uri
+                .path
+                .map(|path| path.into_iter().map(Arc::from).collect()),
+            path_kind: uri.path_kind,
+            qs: uri.qs.map(|qs| {
+                qs.into_iter()
+                    .map(|(k, v)| (Arc::from(k), Arc::from(v)))
+                    .collect()
+            }),
+            fragment: uri.fragment.map(Arc::from),
+        })
+    }
+}
+
+/// Copies the shared strings back out, to use the `URI<String>` API
+///
+/// # Examples
+///
+/// ```
+/// use auris::intern::Interner;
+/// use auris::URI;
+///
+/// let mut interner = Interner::default();
+/// let uri = interner.parse("https://example.com/a?k=v").unwrap();
+///
+/// assert_eq!("https://example.com/a?k=v", URI::<String>::from(&uri).to_string());
+/// ```
+impl From<&URI<Arc<str>>> for URI<String> {
+    fn from(uri: &URI<Arc<str>>) -> Self {
+        let owned = |s: &Arc<str>| s.to_string();
+        URI {
+            scheme: owned(&uri.scheme),
+            authority: Authority {
+                host: owned(&uri.authority.host),
+                userinfo: uri
+                    .authority
+                    .userinfo
+                    .as_ref()
+                    .map(|userinfo| match userinfo {
+                        UserInfo::User(u) => UserInfo::User(owned(u)),
+                        UserInfo::UserAndPassword(u, p) => {
+                            UserInfo::UserAndPassword(owned(u), owned(p))
+                        }
+                    }),
+                port: uri.authority.port,
+            },
+            path: uri
+                .path
+                .as_ref()
+                .map(|path| path.iter().map(owned).collect()),
+            path_kind: uri.path_kind,
+            qs: uri
+                .qs
+                .as_ref()
+                .map(|qs| qs.iter().map(|(k, v)| (owned(k), owned(v))).collect()),
+            fragment: uri.fragment.as_ref().map(owned),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interning_shares_schemes_and_hosts() {
+        let mut interner = Interner::default();
+        let inputs = [
+            "https://u:p@a.example/x?k=v#f",
+            "https://a.example/y",
+            "http://b.example/",
+            "mailto:jane@a.example",
+        ];
+        let uris: Vec<_> = inputs.iter().map(|i| interner.parse(i).unwrap()).collect();
+        // https, a.example, http, b.example, mailto and the empty host
+        assert_eq!(interner.len(), 6);
+        assert!(Arc::ptr_eq(&uris[0].scheme, &uris[1].scheme));
+        assert!(Arc::ptr_eq(
+            &uris[0].authority.host,
+            &uris[1].authority.host
+        ));
+        for (input, uri) in inputs.iter().zip(&uris) {
+            assert_eq!(URI::<String>::from(uri).to_string(), *input);
+        }
+        assert!(interner.parse("not a uri").is_err());
+        assert_eq!(interner.len(), 6);
+    }
+}
@@ -0,0 +1,249 @@
+//! Implementations of traits from other crates, each behind a feature
+
+#[cfg(any(
+    feature = "schemars",
+    feature = "sqlx",
+    feature = "diesel",
+    feature = "clap"
+))]
+use crate::URI;
+#[cfg(feature = "clap")]
+use crate::{ParseError, ParseOptions};
+
+/// A string with the `uri` format
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for URI<String> {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "URI".to_string()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        schemars::schema::SchemaObject {
+            instance_type: Some(schemars::schema::InstanceType::String.into()),
+            format: Some("uri".to_string()),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Stored as text
+#[cfg(feature = "sqlx")]
+impl<DB> sqlx::Type<DB> for URI<String>
+where
+    DB: sqlx::Database,
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<'q, DB> sqlx::Encode<'q, DB> for URI<String>
+where
+    DB: sqlx::Database,
+    String: sqlx::Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut DB::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        self.to_string().encode(buf)
+    }
+}
+
+/// Decodes text, which has to parse as `FromStr` does
+#[cfg(feature = "sqlx")]
+impl<'r, DB> sqlx::Decode<'r, DB> for URI<String>
+where
+    DB: sqlx::Database,
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: DB::ValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        Ok(String::decode(value)?.parse()?)
+    }
+}
+
+/// Stored as text
+#[cfg(feature = "diesel")]
+impl diesel::serialize::ToSql<diesel::sql_types::Text, diesel::pg::Pg> for URI<String> {
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, diesel::pg::Pg>,
+    ) -> diesel::serialize::Result {
+        use std::io::Write;
+
+        write!(out, "{}", self)?;
+        Ok(diesel::serialize::IsNull::No)
+    }
+}
+
+/// Reads text, which has to parse as `FromStr` does
+#[cfg(feature = "diesel")]
+impl<DB> diesel::deserialize::FromSql<diesel::sql_types::Text, DB> for URI<String>
+where
+    DB: diesel::backend::Backend,
+    String: diesel::deserialize::FromSql<diesel::sql_types::Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        Ok(String::from_sql(bytes)?.parse()?)
+    }
+}
+
+/// Parses URI arguments for clap, with options and a list of allowed
+/// schemes
+///
+/// `value_parser!(URI<String>)` works too, as `URI<String>` implements
+/// `FromStr`. This parser adds [`ParseOptions`] and restricting the
+/// schemes. Requires the `clap` feature.
+///
+/// # Examples
+///
+/// ```
+/// use auris::{ParseOptions, URI, UriValueParser};
+/// use clap::{Arg, Command};
+///
+/// let cmd = Command::new("fetch").arg(
+///     Arg::new("url").value_parser(
+///         UriValueParser::new()
+///             .options(ParseOptions::default().strip_whitespace(true))
+///             .schemes(&["http", "https"]),
+///     ),
+/// );
+///
+/// let matches = cmd.clone().try_get_matches_from(["fetch", " https://example.com "]).unwrap();
+/// assert_eq!(matches.get_one::<URI<String>>("url").unwrap().host_str(), "example.com");
+///
+/// let err = cmd.try_get_matches_from(["fetch", "ftp://example.com"]).unwrap_err();
+/// assert!(err.to_string().contains("scheme must be one of http, https"));
+/// ```
+#[cfg(feature = "clap")]
+#[derive(Debug, Clone, Default)]
+pub struct UriValueParser {
+    options: ParseOptions,
+    schemes: Vec<String>,
+}
+
+#[cfg(feature = "clap")]
+impl UriValueParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse with `options` instead of the defaults
+    pub fn options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Only accept URIs with one of `schemes`, compared ignoring ASCII case
+    pub fn schemes(mut self, schemes: &[&str]) -> Self {
+        self.schemes = schemes.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    fn parse(&self, value: &str) -> Result<URI<String>, String> {
+        let uri = URI::parse_with(value, &self.options).map_err(|e: ParseError| e.to_string())?;
+        if !self.schemes.is_empty()
+            && !self
+                .schemes
+                .iter()
+                .any(|s| s.eq_ignore_ascii_case(&uri.scheme))
+        {
+            return Err(format!("scheme must be one of {}", self.schemes.join(", ")));
+        }
+        Ok(uri)
+    }
+}
+
+#[cfg(feature = "clap")]
+impl clap::builder::TypedValueParser for UriValueParser {
+    type Value = URI<String>;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let error = |kind, message: String| {
+            let arg = arg.map_or_else(|| "...".to_string(), |a| a.to_string());
+            clap::Error::raw(kind, format!("invalid value for '{}': {}\n", arg, message))
+                .with_cmd(cmd)
+        };
+        let value = value
+            .to_str()
+            .ok_or_else(|| error(clap::error::ErrorKind::InvalidUtf8, "not UTF-8".to_string()))?;
+        self.parse(value).map_err(|message| {
+            error(
+                clap::error::ErrorKind::ValueValidation,
+                format!("'{}': {}", value, message),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "diesel")]
+    #[test]
+    fn test_diesel_text() {
+        use diesel::deserialize::FromSql;
+        use diesel::expression::AsExpression;
+        use diesel::pg::Pg;
+        use diesel::serialize::ToSql;
+        use diesel::sql_types::Text;
+
+        fn is_text<T: ToSql<Text, Pg> + FromSql<Text, Pg>>() {}
+        fn is_expression<T: AsExpression<Text>>() {}
+        is_text::<crate::URI<String>>();
+        is_expression::<&crate::URI<String>>();
+    }
+
+    #[cfg(feature = "clap")]
+    #[test]
+    fn test_clap_errors() {
+        use clap::{value_parser, Arg, Command};
+
+        let cmd = Command::new("c")
+            .arg(
+                Arg::new("plain")
+                    .long("plain")
+                    .value_parser(value_parser!(crate::URI<String>)),
+            )
+            .arg(
+                Arg::new("parser")
+                    .long("parser")
+                    .value_parser(super::UriValueParser::new()),
+            );
+        let err = cmd
+            .clone()
+            .try_get_matches_from(["c", "--plain", "http://a.com:x"])
+            .unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ValueValidation);
+        let err = cmd
+            .try_get_matches_from(["c", "--parser", "http://a.com /x"])
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("'http://a.com /x': Unexpected input at offset 12"));
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn test_json_schema() {
+        let schema = schemars::schema_for!(crate::URI<String>);
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(json["type"], "string");
+        assert_eq!(json["format"], "uri");
+    }
+}
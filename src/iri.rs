@@ -0,0 +1,187 @@
+//! Converting between IRIs (RFC 3987) and URIs
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::URI;
+//!
+//! let iri = "https://example.com/straße?q=é".parse::<URI<String>>().unwrap();
+//! let uri = iri.to_uri();
+//!
+//! assert_eq!("https://example.com/stra%C3%9Fe?q=%C3%A9", format!("{}", uri));
+//! assert_eq!(uri.to_iri(), iri);
+//! ```
+use crate::visit::Component;
+use crate::{percent, UserInfo, URI};
+use std::borrow::Cow;
+
+/// `ucschar` from RFC 3987, the non-ASCII characters allowed anywhere in
+/// an IRI
+pub(crate) fn is_ucschar(c: char) -> bool {
+    let c = u32::from(c);
+    matches!(c, 0xa0..=0xd7ff | 0xf900..=0xfdcf | 0xfdf0..=0xffef)
+        // planes 1 to 14, but not the last two code points of each
+        || ((0x1_0000..=0xe_fffd).contains(&c) && c & 0xffff <= 0xfffd)
+}
+
+/// `iprivate` from RFC 3987, private use characters allowed in the query
+pub(crate) fn is_iprivate(c: char) -> bool {
+    matches!(
+        u32::from(c),
+        0xe000..=0xf8ff | 0xf_0000..=0xf_fffd | 0x10_0000..=0x10_fffd
+    )
+}
+
+/// Bidi controls: the marks, embeddings, overrides and isolates
+///
+/// RFC 3987 section 4.1 forbids the marks, embeddings and overrides in
+/// IRIs. The arabic letter mark and the isolates came later and reorder
+/// text just the same.
+pub(crate) fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+/// The byte escaped at `i`, if there is a valid escape there
+fn escape_at(bytes: &[u8], i: usize) -> Option<u8> {
+    match bytes.get(i..i + 3) {
+        Some([b'%', hi, lo]) => {
+            let hex = |b: &u8| char::from(*b).to_digit(16);
+            Some((hex(hi)? << 4 | hex(lo)?) as u8)
+        }
+        _ => None,
+    }
+}
+
+/// Decode the escaped UTF-8 sequences that are characters an IRI allows
+///
+/// Escaped ASCII, invalid UTF-8 and characters that aren't allowed stay
+/// escaped.
+fn decode_iri_chars(component: &str, in_query: bool) -> Cow<'_, str> {
+    if !component.contains('%') {
+        return Cow::Borrowed(component);
+    }
+    let bytes = component.as_bytes();
+    let mut decoded = String::with_capacity(component.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'%' {
+            let next = component[i..].find('%').map_or(bytes.len(), |n| i + n);
+            decoded.push_str(&component[i..next]);
+            i = next;
+            continue;
+        }
+        let len = match escape_at(bytes, i) {
+            Some(0xc2..=0xdf) => 2,
+            Some(0xe0..=0xef) => 3,
+            Some(0xf0..=0xf4) => 4,
+            _ => 1,
+        };
+        let sequence: Option<Vec<u8>> = (0..len).map(|n| escape_at(bytes, i + 3 * n)).collect();
+        let c = sequence
+            .filter(|_| len > 1)
+            .and_then(|s| String::from_utf8(s).ok())
+            .and_then(|s| s.chars().next())
+            .filter(|c| !is_bidi_control(*c) && (is_ucschar(*c) || (in_query && is_iprivate(*c))));
+        match c {
+            Some(c) => {
+                decoded.push(c);
+                i += 3 * len;
+            }
+            None => {
+                decoded.push('%');
+                i += 1;
+            }
+        }
+    }
+    Cow::Owned(decoded)
+}
+
+/// Apply `f` to the user and password
+fn map_userinfo<F>(userinfo: &mut UserInfo<String>, f: F)
+where
+    F: Fn(&str) -> Cow<'_, str>,
+{
+    match userinfo {
+        UserInfo::User(user) => *user = f(user).into_owned(),
+        UserInfo::UserAndPassword(user, password) => {
+            *user = f(user).into_owned();
+            *password = f(password).into_owned();
+        }
+    }
+}
+
+impl URI<String> {
+    /// The URI form of an IRI, with every non-ASCII character percent
+    /// encoded as UTF-8
+    ///
+    /// This includes the host, as RFC 3987 section 3.1 allows. Hosts that
+    /// will be looked up in the DNS should be converted with IDNA instead,
+    /// which `URI::host_to_ascii` does with the `idna` feature.
+    pub fn to_uri(&self) -> URI<String> {
+        let mut uri = self.clone();
+        uri.map_components(|_, value| {
+            if let Cow::Owned(encoded) = percent::encode_non_ascii(value) {
+                *value = encoded;
+            }
+        });
+        if let Some(userinfo) = uri.authority.userinfo.as_mut() {
+            map_userinfo(userinfo, percent::encode_non_ascii);
+        }
+        uri
+    }
+
+    /// The IRI form of a URI, with escaped UTF-8 decoded where the result
+    /// is a character an IRI allows
+    ///
+    /// Escaped ASCII stays escaped, so the meaning of the URI doesn't
+    /// change, as do invalid UTF-8 and bidi formatting characters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "http://example.com/%E6%97%A5%E6%9C%AC%2F%FF?q=%E2%80%8F".parse::<URI<String>>().unwrap();
+    ///
+    /// assert_eq!("http://example.com/日本%2F%FF?q=%E2%80%8F", format!("{}", uri.to_iri()));
+    /// ```
+    pub fn to_iri(&self) -> URI<String> {
+        let mut iri = self.clone();
+        iri.map_components(|component, value| {
+            let in_query = matches!(component, Component::QueryKey | Component::QueryValue);
+            if let Cow::Owned(decoded) = decode_iri_chars(value, in_query) {
+                *value = decoded;
+            }
+        });
+        if let Some(userinfo) = iri.authority.userinfo.as_mut() {
+            map_userinfo(userinfo, |part| decode_iri_chars(part, false));
+        }
+        iri
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_iri_chars() {
+        assert_eq!(decode_iri_chars("caf%C3%A9%20", false), "café%20");
+        assert_eq!(decode_iri_chars("%C3%", false), "%C3%");
+        assert_eq!(decode_iri_chars("%C3%A9%C3", false), "é%C3");
+        assert_eq!(decode_iri_chars("%EE%80%80", false), "%EE%80%80");
+        assert_eq!(decode_iri_chars("%EE%80%80", true), "\u{e000}");
+        assert_eq!(decode_iri_chars("%EF%BF%BF", true), "%EF%BF%BF");
+    }
+
+    #[test]
+    fn test_userinfo_round_trip() {
+        let iri = "ftp://jörg:pässword@h/".parse::<URI<String>>().unwrap();
+        let uri = iri.to_uri();
+        assert_eq!(uri.to_string(), "ftp://j%C3%B6rg:p%C3%A4ssword@h/");
+        assert_eq!(uri.to_iri(), iri);
+    }
+}
@@ -0,0 +1,117 @@
+//! Turning authorities into socket addresses
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::URI;
+//! use std::net::SocketAddr;
+//!
+//! let uri = "https://[::1]/health".parse::<URI<String>>().unwrap();
+//!
+//! assert_eq!(uri.ip_socket_addr(), Some("[::1]:443".parse::<SocketAddr>().unwrap()));
+//! ```
+use crate::{schemes, Authority, Host, URI};
+use std::net::{IpAddr, SocketAddr};
+#[cfg(feature = "net")]
+use std::{io, net::ToSocketAddrs, vec};
+
+impl Authority<String> {
+    /// The socket address when the host is an IP address, without any DNS
+    /// lookup
+    ///
+    /// `default_port` is used when the authority has no port.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "redis://10.0.0.7:6380".parse::<URI<String>>().unwrap();
+    /// let addr = uri.authority.ip_socket_addr(6379).unwrap();
+    ///
+    /// assert_eq!(addr.to_string(), "10.0.0.7:6380");
+    /// ```
+    pub fn ip_socket_addr(&self, default_port: u16) -> Option<SocketAddr> {
+        let ip = match Host::from_authority_host(&self.host)? {
+            Host::Ipv4(ip) => IpAddr::V4(ip),
+            Host::Ipv6(ip) => IpAddr::V6(ip),
+            Host::Domain(_) => return None,
+        };
+        Some(SocketAddr::new(ip, self.port.unwrap_or(default_port)))
+    }
+
+    /// Resolve the authority to socket addresses, doing a blocking DNS lookup
+    /// for domain names
+    ///
+    /// `default_port` is used when the authority has no port. Requires the
+    /// `net` feature.
+    #[cfg(feature = "net")]
+    pub fn to_socket_addrs(&self, default_port: u16) -> io::Result<vec::IntoIter<SocketAddr>> {
+        if let Some(addr) = self.ip_socket_addr(default_port) {
+            return Ok(vec![addr].into_iter());
+        }
+        let host = crate::normalize::decode_reg_name(&self.host);
+        (&*host, self.port.unwrap_or(default_port)).to_socket_addrs()
+    }
+}
+
+impl URI<String> {
+    /// The socket address when the host is an IP address, using the
+    /// scheme's default port when there is no port
+    pub fn ip_socket_addr(&self) -> Option<SocketAddr> {
+        let port = self
+            .authority
+            .port
+            .or_else(|| schemes::default_port(&self.scheme))?;
+        self.authority.ip_socket_addr(port)
+    }
+
+    /// Resolve the host and effective port to socket addresses, doing a
+    /// blocking DNS lookup for domain names
+    ///
+    /// Fails when there is no port and the scheme has no default port.
+    /// Requires the `net` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "http://127.0.0.1/".parse::<URI<String>>().unwrap();
+    /// let addrs: Vec<_> = uri.to_socket_addrs().unwrap().collect();
+    ///
+    /// assert_eq!(addrs, vec!["127.0.0.1:80".parse().unwrap()]);
+    /// ```
+    #[cfg(feature = "net")]
+    pub fn to_socket_addrs(&self) -> io::Result<vec::IntoIter<SocketAddr>> {
+        let port = self
+            .authority
+            .port
+            .or_else(|| schemes::default_port(&self.scheme))
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "no port for this scheme")
+            })?;
+        self.authority.to_socket_addrs(port)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ip_socket_addr() {
+        let addr = |s: &str| s.parse::<URI<String>>().unwrap().ip_socket_addr();
+        assert_eq!(addr("http://127.0.0.1:8080"), "127.0.0.1:8080".parse().ok());
+        assert_eq!(addr("ws://[fe80::1]"), "[fe80::1]:80".parse().ok());
+        assert_eq!(addr("http://localhost"), None);
+        assert_eq!(addr("custom://127.0.0.1"), None);
+    }
+
+    #[cfg(feature = "net")]
+    #[test]
+    fn test_to_socket_addrs_needs_a_port() {
+        let uri = "custom://127.0.0.1".parse::<URI<String>>().unwrap();
+        assert!(uri.to_socket_addrs().is_err());
+    }
+}
@@ -0,0 +1,428 @@
+//! Normalization of URIs, so equivalent URIs compare equal
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::URI;
+//!
+//! let mut uri = "http://Ex%61mple.COM/".parse::<URI<String>>().unwrap();
+//! uri.normalize_host();
+//!
+//! assert_eq!("http://example.com/", format!("{}", uri));
+//! ```
+use crate::path::DotDotPolicy;
+use crate::schemes::ipfs::Namespace;
+use crate::visit::Component;
+use crate::{percent, schemes, Host, UserInfo, URI};
+use std::borrow::Cow;
+
+/// 64 bit FNV-1a, which unlike `DefaultHasher` is the same everywhere
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// Hash `bytes` followed by a separator that can't appear in a `&str`
+    fn component(&mut self, bytes: &[u8]) {
+        for b in bytes.iter().chain(&[0xff]) {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Characters a reg-name may contain besides percent escapes
+fn is_reg_name_char(c: char) -> bool {
+    !c.is_ascii() || c.is_ascii_alphanumeric() || "-._~!$&'()*+,;=".contains(c)
+}
+
+/// Percent decode a reg-name host
+///
+/// IP literals are left as they are, as are hosts whose escapes decode to
+/// characters that would change the meaning of the URI, such as `/` or `@`.
+pub(crate) fn decode_reg_name(host: &str) -> Cow<'_, str> {
+    if host.starts_with('[') {
+        return Cow::Borrowed(host);
+    }
+    let decoded = percent::decode(host);
+    if decoded.chars().all(is_reg_name_char) {
+        decoded
+    } else {
+        Cow::Borrowed(host)
+    }
+}
+
+/// Apply NFC to the decoded form of a component
+///
+/// Characters that have to stay escaped are escaped again, and non-ASCII
+/// characters are only escaped again if the component was ASCII to begin
+/// with. Components that don't decode to UTF-8 are left alone.
+#[cfg(feature = "nfc")]
+fn nfc_component(component: &mut String) {
+    use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+    let decoded = match std::str::from_utf8(&percent::decode_bytes(component)) {
+        Ok(decoded) if !is_nfc(decoded) => decoded.nfc().collect::<String>(),
+        _ => return,
+    };
+    let keep_ascii = component.is_ascii();
+    let mut normalized = String::with_capacity(decoded.len());
+    for c in decoded.chars() {
+        if (c.is_ascii() && !(c.is_ascii_alphanumeric() || "-._~!$'()*+,;:@".contains(c)))
+            || (keep_ascii && !c.is_ascii())
+        {
+            let mut buf = [0; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                normalized.push_str(&format!("%{:02X}", b));
+            }
+        } else {
+            normalized.push(c);
+        }
+    }
+    *component = normalized;
+}
+
+impl URI<String> {
+    /// Apply Unicode NFC normalization to the decoded host, path segments,
+    /// query pairs and fragment
+    ///
+    /// Visually identical URIs written with different code point sequences,
+    /// such as a precomposed `é` and `e` followed by a combining accent,
+    /// compare equal afterwards. Requires the `nfc` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let mut composed = "http://example.com/caf%C3%A9".parse::<URI<String>>().unwrap();
+    /// let mut decomposed = "http://example.com/cafe%CC%81".parse::<URI<String>>().unwrap();
+    /// composed.normalize_nfc();
+    /// decomposed.normalize_nfc();
+    ///
+    /// assert_eq!(composed, decomposed);
+    /// assert_eq!("http://example.com/caf%C3%A9", format!("{}", decomposed));
+    /// ```
+    #[cfg(feature = "nfc")]
+    pub fn normalize_nfc(&mut self) {
+        self.map_components(|component, value| {
+            if component != crate::visit::Component::Scheme {
+                nfc_component(value)
+            }
+        });
+    }
+
+    /// A hash of the normalized URI that stays the same across runs,
+    /// platforms and releases, for use as a cache or dedup key
+    ///
+    /// The scheme and host are compared case insensitively, except for the
+    /// case sensitive CID of `ipfs:` and `ipns:` URIs, escapes of
+    /// unreserved characters are decoded and other escapes compared
+    /// regardless of hex case, a default port is the same as no port, and
+    /// query pairs are sorted. The fragment is included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let a = "HTTP://Example.com:80/%7euser/a%2fb?x=1&y=2".parse::<URI<String>>().unwrap();
+    /// let b = "http://example.com/~user/a%2Fb?y=2&x=1".parse::<URI<String>>().unwrap();
+    /// let c = "http://example.com/~user/a/b?y=2&x=1".parse::<URI<String>>().unwrap();
+    ///
+    /// assert_eq!(a.canonical_hash(), b.canonical_hash());
+    /// assert_ne!(a.canonical_hash(), c.canonical_hash());
+    /// ```
+    pub fn canonical_hash(&self) -> u64 {
+        self.canonical_hash_of(true)
+    }
+
+    /// [`URI::canonical_hash`] without the fragment, so URIs differing only
+    /// in their fragments share a key, as with [`URI::eq_ignoring_fragment`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let a = "https://example.com/page#a".parse::<URI<String>>().unwrap();
+    /// let b = "https://example.com/page#b".parse::<URI<String>>().unwrap();
+    ///
+    /// assert_eq!(a.canonical_hash_ignoring_fragment(), b.canonical_hash_ignoring_fragment());
+    /// ```
+    pub fn canonical_hash_ignoring_fragment(&self) -> u64 {
+        self.canonical_hash_of(false)
+    }
+
+    fn canonical_hash_of(&self, fragment: bool) -> u64 {
+        let mut hasher = Fnv1a::new();
+        let scheme = self.scheme.to_ascii_lowercase();
+        hasher.component(scheme.as_bytes());
+        hasher.component(&[self.path_kind as u8]);
+
+        let userinfo = self.authority.userinfo.as_ref().map(|u| u.to_string());
+        hasher.component(percent::normalize_escapes(userinfo.as_deref().unwrap_or("")).as_bytes());
+        hasher.component(self.normalized_host().as_bytes());
+        let port = self
+            .authority
+            .port
+            .filter(|port| Some(*port) != schemes::default_port(&scheme));
+        hasher.component(&port.map_or([0; 3], |p| {
+            let [hi, lo] = p.to_be_bytes();
+            [1, hi, lo]
+        }));
+
+        for segment in self.path.iter().flatten() {
+            hasher.component(percent::normalize_escapes(segment).as_bytes());
+        }
+        hasher.component(&[]);
+
+        if let Some(qs) = self.qs.as_ref() {
+            let mut pairs: Vec<_> = qs
+                .iter()
+                .map(|(k, v)| (percent::normalize_escapes(k), percent::normalize_escapes(v)))
+                .collect();
+            pairs.sort();
+            for (k, v) in pairs {
+                hasher.component(k.as_bytes());
+                hasher.component(v.as_bytes());
+            }
+        }
+        hasher.component(&[]);
+
+        if let Some(fragment) = self.fragment.as_ref().filter(|_| fragment) {
+            hasher.component(percent::normalize_escapes(fragment).as_bytes());
+        }
+        hasher.0
+    }
+
+    /// The percent decoded host, lowercased unless it is an IPFS or IPNS
+    /// CID, whose case matters
+    fn normalized_host(&self) -> String {
+        let host = decode_reg_name(&self.authority.host);
+        if Namespace::from_scheme(&self.scheme).is_some() {
+            host.into_owned()
+        } else {
+            host.to_ascii_lowercase()
+        }
+    }
+
+    /// Rewrite the URI into a normal form, so equivalent URIs display the
+    /// same
+    ///
+    /// The scheme and host are lowercased, except for the CID of `ipfs:`
+    /// and `ipns:` URIs, a default port is dropped,
+    /// escapes are normalized as [`URI::canonical_hash`] does, and dot
+    /// segments are removed, which the hash doesn't do. Query pairs keep
+    /// their map order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let mut uri = "HTTP://Example.COM:80/a/./b/../%7ec%2f".parse::<URI<String>>().unwrap();
+    /// uri.canonicalize();
+    ///
+    /// assert_eq!("http://example.com/a/~c%2F", format!("{}", uri));
+    /// ```
+    pub fn canonicalize(&mut self) {
+        self.scheme.make_ascii_lowercase();
+        self.normalize_host();
+        if self.authority.port.is_some()
+            && self.authority.port == schemes::default_port(&self.scheme)
+        {
+            self.authority.port = None;
+        }
+        let normalize = |value: &mut String| {
+            if let Cow::Owned(normalized) = percent::normalize_escapes(value) {
+                *value = normalized;
+            }
+        };
+        match self.authority.userinfo.as_mut() {
+            Some(UserInfo::User(user)) => normalize(user),
+            Some(UserInfo::UserAndPassword(user, password)) => {
+                normalize(user);
+                normalize(password);
+            }
+            None => (),
+        }
+        self.map_components(|component, value| {
+            if component != Component::Scheme && component != Component::Host {
+                normalize(value)
+            }
+        });
+        // dropping excess `..` never fails
+        let _ = self.remove_dot_segments(DotDotPolicy::Drop);
+    }
+
+    /// The SURT (Sort-friendly URI Reordering Transform) form used by web
+    /// archives to key captures, such as `com,example,www)/path?a=1&b=2`
+    ///
+    /// The URI is canonicalized first. The scheme, userinfo and fragment
+    /// are dropped, domain labels are reversed and joined by `,`, and query
+    /// pairs are sorted. IP hosts are kept as they are, and URIs without an
+    /// authority are only canonicalized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "https://WWW.Example.com:443/a/../Path?b=2&a=1#top".parse::<URI<String>>().unwrap();
+    ///
+    /// assert_eq!(uri.to_surt(), "com,example,www)/Path?a=1&b=2");
+    /// ```
+    pub fn to_surt(&self) -> String {
+        let mut uri = self.clone();
+        uri.canonicalize();
+        if !uri.has_authority() {
+            return uri.to_string();
+        }
+        let host = uri.authority.host.trim_end_matches('.');
+        let mut surt = match Host::from_authority_host(host) {
+            Some(Host::Domain(_)) => host.rsplit('.').collect::<Vec<_>>().join(","),
+            _ => host.to_string(),
+        };
+        if let Some(port) = uri.authority.port {
+            surt.push(':');
+            surt.push_str(&port.to_string());
+        }
+        surt.push(')');
+        match uri.path.as_ref().filter(|path| !path.is_empty()) {
+            Some(path) => {
+                for segment in path {
+                    surt.push('/');
+                    surt.push_str(segment);
+                }
+            }
+            None => surt.push('/'),
+        }
+        if let Some(qs) = uri.qs.as_ref() {
+            let mut pairs: Vec<_> = qs.iter().collect();
+            pairs.sort();
+            surt.push('?');
+            for (i, (k, v)) in pairs.into_iter().enumerate() {
+                if i > 0 {
+                    surt.push('&');
+                }
+                surt.push_str(k);
+                surt.push('=');
+                surt.push_str(v);
+            }
+        }
+        surt
+    }
+
+    /// Percent decode and lowercase the host
+    ///
+    /// Escapes are only decoded when the result is still a valid host, so
+    /// `http://a%2Fb/` keeps its host as is. The host of an `ipfs:` or
+    /// `ipns:` URI is a case sensitive CID and is not lowercased.
+    pub fn normalize_host(&mut self) {
+        self.authority.host = self.normalized_host();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_surt() {
+        let surt = |s: &str| s.parse::<URI<String>>().unwrap().to_surt();
+        assert_eq!(surt("http://Example.com."), "com,example)/");
+        assert_eq!(
+            surt("http://u:p@example.com:8080/a/"),
+            "com,example:8080)/a/"
+        );
+        assert_eq!(surt("http://127.0.0.1/x?"), "127.0.0.1)/x?");
+        assert_eq!(surt("http://[::1]/"), "[::1])/");
+        assert_eq!(surt("https://example.com/%7ea"), "com,example)/~a");
+        assert_eq!(surt("URN:isbn:123"), "urn:isbn:123");
+    }
+
+    #[test]
+    fn test_decode_reg_name() {
+        assert_eq!(decode_reg_name("ex%61mple.com"), "example.com");
+        assert_eq!(decode_reg_name("b%C3%BCcher.de"), "bücher.de");
+        assert_eq!(decode_reg_name("a%2Fb"), "a%2Fb");
+        assert_eq!(decode_reg_name("a%40b"), "a%40b");
+        assert_eq!(decode_reg_name("[::1]"), "[::1]");
+    }
+
+    #[test]
+    fn test_canonical_hash_is_stable() {
+        let uri = "http://example.com/a?b=c#d".parse::<URI<String>>().unwrap();
+        assert_eq!(uri.canonical_hash(), 0x21a4_6c88_3c12_1aab);
+
+        let with_query = "http://example.com/a?".parse::<URI<String>>().unwrap();
+        let without = "http://example.com/a".parse::<URI<String>>().unwrap();
+        assert_eq!(with_query.canonical_hash(), without.canonical_hash());
+    }
+
+    #[test]
+    fn test_canonical_hash_keeps_cid_case() {
+        let hash = |s: &str| s.parse::<URI<String>>().unwrap().canonical_hash();
+        assert_ne!(
+            hash("ipfs://QmXoypizjW3WknFiJnKLwHCnL72vedxjQkDDP1mXWo6uco/wiki"),
+            hash("ipfs://qmxoypizjw3wknfijnklwhcnl72vedxjqkddp1mxwo6uco/wiki")
+        );
+        assert_eq!(hash("IPNS://Docs/"), hash("ipns://Docs/"));
+        assert_ne!(hash("ipns://Docs/"), hash("ipns://docs/"));
+        assert_eq!(hash("http://Docs/"), hash("http://docs/"));
+    }
+
+    #[test]
+    fn test_canonicalize_keeps_cid_case() {
+        let cid = "ipfs://QmXoypizjW3WknFiJnKLwHCnL72vedxjQkDDP1mXWo6uco/wiki";
+        let mut uri = cid.parse::<URI<String>>().unwrap();
+        assert_eq!(
+            uri.to_surt(),
+            "QmXoypizjW3WknFiJnKLwHCnL72vedxjQkDDP1mXWo6uco)/wiki"
+        );
+        uri.canonicalize();
+        assert_eq!(uri.to_string(), cid);
+
+        let mut uri = "IPNS://Docs.Example/".parse::<URI<String>>().unwrap();
+        uri.normalize_host();
+        assert_eq!(uri.to_string(), "IPNS://Docs.Example/");
+    }
+
+    #[test]
+    fn test_canonicalize_keeps_the_canonical_hash() {
+        for input in &[
+            "HTTPS://u%7e:p@H%41:443/a?%6b=%2f#%7E",
+            "urn:A:b",
+            "http://h:8080/x/",
+        ] {
+            let uri = input.parse::<URI<String>>().unwrap();
+            let mut canonical = uri.clone();
+            canonical.canonicalize();
+            assert_eq!(
+                canonical.canonical_hash(),
+                uri.canonical_hash(),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[cfg(feature = "nfc")]
+    #[test]
+    fn test_nfc_component() {
+        let mut raw = "cafe\u{301}".to_string();
+        nfc_component(&mut raw);
+        assert_eq!(raw, "caf\u{e9}");
+
+        let mut escaped = "a%20e%CC%81%2F".to_string();
+        nfc_component(&mut escaped);
+        assert_eq!(escaped, "a%20%C3%A9%2F");
+
+        let mut untouched = "e%CC%81%FF".to_string();
+        nfc_component(&mut untouched);
+        assert_eq!(untouched, "e%CC%81%FF");
+    }
+}
@@ -0,0 +1,613 @@
+//! Options controlling how URIs are parsed
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::{ParseOptions, URI};
+//!
+//! let options = ParseOptions::default().backslash_as_slash(true);
+//! let uri = URI::parse_with(r"http:\\example.com\a\b", &options).unwrap();
+//!
+//! assert_eq!("http://example.com/a/b", format!("{}", uri));
+//!
+//! let options = ParseOptions::default().strip_whitespace(true);
+//! let uri = URI::parse_with("  https://example.com/long\n/path\n", &options).unwrap();
+//!
+//! assert_eq!("https://example.com/long/path", format!("{}", uri));
+//! ```
+use crate::host::parse_numeric_ipv4;
+use crate::iri::{is_iprivate, is_ucschar};
+use crate::{parsers, unbracketed_ipv6_authority, QueryStore};
+use crate::{AurisParseErrorKind, ParseError, URI};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+
+/// Schemes browsers give special treatment, such as accepting `\` for `/`
+const SPECIAL_SCHEMES: &[&str] = &["ftp", "file", "http", "https", "ws", "wss"];
+
+/// What to do with query keys that appear more than once
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum DuplicateKeys {
+    /// Keep the last value, as `FromStr` does
+    #[default]
+    LastWins,
+    /// Keep the first value
+    FirstWins,
+    /// Fail with `AurisParseErrorKind::DuplicateQueryKey`
+    Error,
+    /// Keep every pair, in the order they appear
+    ///
+    /// This needs a [`QueryStore`] that can hold repeated keys, such as
+    /// [`QueryPairs`](crate::QueryPairs). Stores with one value per key,
+    /// such as [`QueryMap`](crate::QueryMap), fail as with `Error` instead
+    /// of dropping values.
+    CollectAll,
+}
+
+/// Scheme specific rules checked while parsing with
+/// [`ParseOptions::validator`]
+///
+/// Any `Fn(&URI<String>) -> Result<(), &'static str>` is a validator, the
+/// error is the reason the URI was rejected.
+pub trait SchemeValidator {
+    fn validate(&self, uri: &URI<String>) -> Result<(), &'static str>;
+}
+
+impl<F> SchemeValidator for F
+where
+    F: Fn(&URI<String>) -> Result<(), &'static str>,
+{
+    fn validate(&self, uri: &URI<String>) -> Result<(), &'static str> {
+        self(uri)
+    }
+}
+
+/// A validator and the scheme it applies to
+#[derive(Clone)]
+struct Validator {
+    scheme: String,
+    validator: Arc<dyn SchemeValidator + Send + Sync>,
+}
+
+impl fmt::Debug for Validator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Validator")
+            .field("scheme", &self.scheme)
+            .finish()
+    }
+}
+
+/// Options for [`URI::parse_with`](crate::URI::parse_with)
+///
+/// The defaults match `FromStr`.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    backslash_as_slash: bool,
+    strip_whitespace: bool,
+    empty_port_as_absent: bool,
+    validators: Vec<Validator>,
+    duplicate_keys: DuplicateKeys,
+    iri: bool,
+    strict_ipv4: bool,
+    numeric_hosts: bool,
+}
+
+impl ParseOptions {
+    /// Treat `\` as `/` before the query for special schemes (`http`,
+    /// `https`, `ws`, `wss`, `ftp` and `file`), as browsers do
+    pub fn backslash_as_slash(mut self, enabled: bool) -> Self {
+        self.backslash_as_slash = enabled;
+        self
+    }
+
+    /// Trim leading and trailing C0 controls and spaces, and remove tabs and
+    /// newlines anywhere in the input, as the WHATWG URL standard does
+    ///
+    /// URIs copied out of emails and documents often pick these up.
+    pub fn strip_whitespace(mut self, enabled: bool) -> Self {
+        self.strip_whitespace = enabled;
+        self
+    }
+
+    /// Treat a `:` with no port after it, as in `http://example.com:/`, as
+    /// if there was no port instead of failing
+    pub fn empty_port_as_absent(mut self, enabled: bool) -> Self {
+        self.empty_port_as_absent = enabled;
+        self
+    }
+
+    /// Parse the input as an IRI (RFC 3987)
+    ///
+    /// `FromStr` lets any non-ASCII character through. In IRI mode they
+    /// are kept as Unicode too, but have to be `ucschar`s, or in the query
+    /// also `iprivate`s. Anything else, such as a noncharacter or a private
+    /// use character in the path, fails with
+    /// [`AurisParseErrorKind::DisallowedCharacter`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::{AurisParseErrorKind, ParseOptions, URI};
+    ///
+    /// let options = ParseOptions::default().iri(true);
+    /// let iri = URI::parse_with("https://例え.jp/引き割り?q=é#fragmént", &options).unwrap();
+    /// assert_eq!(iri.host_str(), "例え.jp");
+    ///
+    /// let err = URI::parse_with("https://example.com/\u{e000}", &options).unwrap_err();
+    /// assert_eq!(err.kind(), AurisParseErrorKind::DisallowedCharacter { offset: 20 });
+    /// ```
+    pub fn iri(mut self, enabled: bool) -> Self {
+        self.iri = enabled;
+        self
+    }
+
+    /// Check the non-ASCII characters of an IRI, see [`ParseOptions::iri`]
+    pub(crate) fn check_iri(&self, input: &str) -> Result<(), ParseError> {
+        if !self.iri || input.is_ascii() {
+            return Ok(());
+        }
+        let (mut in_query, mut in_fragment) = (false, false);
+        for (offset, c) in input.char_indices() {
+            match c {
+                // `?` and `#` are data in the fragment
+                '?' if !in_fragment => in_query = true,
+                '#' if !in_fragment => (in_query, in_fragment) = (false, true),
+                c if c.is_ascii() || is_ucschar(c) || (in_query && is_iprivate(c)) => (),
+                _ => return Err(AurisParseErrorKind::DisallowedCharacter { offset }.into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reject hosts made of digits and dots that aren't a valid dotted
+    /// quad, rather than taking them for domains
+    ///
+    /// Leading zeros, out of range octets and the wrong number of octets
+    /// all fail, as different resolvers read them differently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::{AurisParseErrorKind, ParseOptions, URI};
+    ///
+    /// let options = ParseOptions::default().strict_ipv4(true);
+    /// assert!(URI::parse_with("http://192.168.1.1/", &options).is_ok());
+    ///
+    /// let err = URI::parse_with("http://192.168.01.1/", &options).unwrap_err();
+    /// assert_eq!(err.kind(), AurisParseErrorKind::Rejected("invalid IPv4 address"));
+    /// assert!(URI::parse_with("http://999.1.1.1/", &options).is_err());
+    /// ```
+    pub fn strict_ipv4(mut self, enabled: bool) -> Self {
+        self.strict_ipv4 = enabled;
+        self
+    }
+
+    /// Read hosts ending in a number as browsers do, and rewrite them as
+    /// dotted quads
+    ///
+    /// Each of up to four parts may be decimal, octal with a leading `0`,
+    /// or hex with `0x`, and the last part fills the remaining octets.
+    /// Security tooling needs this to see the address behind obfuscated
+    /// hosts. Hosts ending in a number that isn't a valid address fail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::{ParseOptions, URI};
+    ///
+    /// let options = ParseOptions::default().numeric_hosts(true);
+    /// for input in &["http://2130706433/", "http://0x7f.1/", "http://0177.0.0.1/"] {
+    ///     let uri = URI::parse_with(input, &options).unwrap();
+    ///     assert_eq!(uri.host_str(), "127.0.0.1");
+    /// }
+    /// assert!(URI::parse_with("http://1.2.3.256/", &options).is_err());
+    /// ```
+    pub fn numeric_hosts(mut self, enabled: bool) -> Self {
+        self.numeric_hosts = enabled;
+        self
+    }
+
+    /// Rewrite and check the host according to the IPv4 options
+    pub(crate) fn process_host(&self, host: &mut String) -> Result<(), ParseError> {
+        if self.numeric_hosts {
+            if let Some(ip) = parse_numeric_ipv4(host)? {
+                *host = ip.to_string();
+            }
+        }
+        self.check_ipv4(host)
+    }
+
+    /// Check an IPv4 looking host, see [`ParseOptions::strict_ipv4`]
+    fn check_ipv4(&self, host: &str) -> Result<(), ParseError> {
+        let looks_like_ipv4 =
+            host.contains('.') && host.bytes().all(|b| b.is_ascii_digit() || b == b'.');
+        if self.strict_ipv4 && looks_like_ipv4 && host.parse::<Ipv4Addr>().is_err() {
+            return Err(AurisParseErrorKind::Rejected("invalid IPv4 address").into());
+        }
+        Ok(())
+    }
+
+    /// How to handle repeated query keys
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::{DuplicateKeys, ParseOptions, QueryPairs, URI};
+    ///
+    /// let input = "https://example.com/?tag=a&tag=b";
+    ///
+    /// let options = ParseOptions::default().duplicate_keys(DuplicateKeys::CollectAll);
+    /// let uri: URI<String, QueryPairs<String, String>> = options.parse(input).unwrap();
+    /// let tags: Vec<_> = uri.qs.unwrap().into_iter().map(|(_, v)| v).collect();
+    /// assert_eq!(tags, ["a", "b"]);
+    ///
+    /// let options = ParseOptions::default().duplicate_keys(DuplicateKeys::FirstWins);
+    /// assert_eq!(URI::parse_with(input, &options).unwrap().qs.unwrap()["tag"], "a");
+    ///
+    /// let options = ParseOptions::default().duplicate_keys(DuplicateKeys::Error);
+    /// assert!(URI::parse_with(input, &options).is_err());
+    /// ```
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Build the query store from its pairs, according to the duplicate
+    /// policy
+    ///
+    /// Repeated keys are found with a map of the pairs kept so far, so
+    /// stores that scan for a key, such as [`QueryPairs`](crate::QueryPairs),
+    /// don't make parsing quadratic.
+    pub(crate) fn collect_query<Q: QueryStore>(
+        &self,
+        pairs: Vec<(&str, &str)>,
+    ) -> Result<Q, ParseError> {
+        let mut positions: HashMap<&str, usize> = HashMap::with_capacity(pairs.len());
+        let mut kept: Vec<(&str, &str)> = Vec::with_capacity(pairs.len());
+        for (k, v) in pairs {
+            match (positions.get(k), self.duplicate_keys) {
+                (Some(&position), DuplicateKeys::LastWins) => kept[position].1 = v,
+                (Some(_), DuplicateKeys::FirstWins) => (),
+                (Some(_), DuplicateKeys::Error) => {
+                    return Err(AurisParseErrorKind::DuplicateQueryKey.into())
+                }
+                (None, _) | (Some(_), DuplicateKeys::CollectAll) => {
+                    positions.entry(k).or_insert(kept.len());
+                    kept.push((k, v));
+                }
+            }
+        }
+        let mut store = Q::default();
+        for (k, v) in kept {
+            if !store.push_pair(k.to_string(), v.to_string()) {
+                return Err(AurisParseErrorKind::DuplicateQueryKey.into());
+            }
+        }
+        Ok(store)
+    }
+
+    /// Check URIs with `scheme` (case insensitive) using `validator`
+    ///
+    /// Validators run in the order they were added, the first to fail
+    /// decides the error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::{AurisParseErrorKind, ParseOptions, URI};
+    ///
+    /// let options = ParseOptions::default().validator("https", |uri: &URI<String>| {
+    ///     match uri.authority.userinfo {
+    ///         Some(_) => Err("https must not have userinfo"),
+    ///         None => Ok(()),
+    ///     }
+    /// });
+    ///
+    /// let err = URI::parse_with("https://user@example.com", &options).unwrap_err();
+    /// assert_eq!(err.kind(), AurisParseErrorKind::Rejected("https must not have userinfo"));
+    /// assert!(URI::parse_with("http://user@example.com", &options).is_ok());
+    /// ```
+    pub fn validator<V>(mut self, scheme: &str, validator: V) -> Self
+    where
+        V: SchemeValidator + Send + Sync + 'static,
+    {
+        self.validators.push(Validator {
+            scheme: scheme.to_string(),
+            validator: Arc::new(validator),
+        });
+        self
+    }
+
+    /// Run the validators for the scheme of `uri`
+    ///
+    /// Validators take a `URI<String>`, so other query stores are copied
+    /// into a [`QueryMap`](crate::QueryMap) for them.
+    pub(crate) fn validate<Q: QueryStore>(&self, uri: &URI<String, Q>) -> Result<(), &'static str> {
+        let mut validators = self
+            .validators
+            .iter()
+            .filter(|v| v.scheme.eq_ignore_ascii_case(&uri.scheme))
+            .peekable();
+        if validators.peek().is_none() {
+            return Ok(());
+        }
+        let uri = URI {
+            scheme: uri.scheme.clone(),
+            authority: uri.authority.clone(),
+            path: uri.path.clone(),
+            path_kind: uri.path_kind,
+            qs: uri
+                .qs
+                .as_ref()
+                .map(|qs| qs.pairs().map(|(k, v)| (k.clone(), v.clone())).collect()),
+            fragment: uri.fragment.clone(),
+        };
+        validators.try_for_each(|v| v.validator.validate(&uri))
+    }
+
+    /// Parse a URI with these options into any [`QueryStore`]
+    ///
+    /// [`URI::parse_with`] parses into the default [`QueryMap`](crate::QueryMap).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::{ParseOptions, QueryPairs, URI};
+    ///
+    /// let options = ParseOptions::default().strip_whitespace(true);
+    /// let uri: URI<String, QueryPairs<String, String>> =
+    ///     options.parse(" https://example.com/?b=1&a=2 ").unwrap();
+    ///
+    /// assert_eq!("https://example.com/?b=1&a=2", format!("{}", uri));
+    /// ```
+    pub fn parse<Q: QueryStore>(&self, s: &str) -> Result<URI<String, Q>, ParseError> {
+        self.parse_preprocessed(&self.preprocess(s))
+    }
+
+    /// Parse input that [`ParseOptions::preprocess`] has already rewritten
+    pub(crate) fn parse_preprocessed<Q: QueryStore>(
+        &self,
+        input: &str,
+    ) -> Result<URI<String, Q>, ParseError> {
+        self.check_iri(input)?;
+        match parsers::uri_with_query_pairs(input) {
+            Ok(("", (obj, pairs))) => {
+                let mut uri = obj.to_owned_without_query();
+                uri.qs = pairs.map(|p| self.collect_query(p)).transpose()?;
+                self.process_host(&mut uri.authority.host)?;
+                self.validate(&uri).map_err(AurisParseErrorKind::Rejected)?;
+                Ok(uri)
+            }
+            _ if unbracketed_ipv6_authority(input) => {
+                Err(AurisParseErrorKind::UnbracketedIpv6.into())
+            }
+            Ok((remaining, _)) => {
+                let offset = input.len() - remaining.len();
+                Err(AurisParseErrorKind::TrailingInput { offset }.into())
+            }
+            Err(e) => Err(ParseError::from_nom(e).with_scheme_suggestion(input)),
+        }
+    }
+
+    /// Whether any of the lenient options rewrite the input before parsing
+    pub(crate) fn rewrites_input(&self) -> bool {
+        self.strip_whitespace || self.backslash_as_slash || self.empty_port_as_absent
+    }
+
+    /// Rewrite the input according to the lenient options
+    pub(crate) fn preprocess<'a>(&self, input: &'a str) -> Cow<'a, str> {
+        if !self.rewrites_input() {
+            return Cow::Borrowed(input);
+        }
+        let mut rewritten = String::with_capacity(input.len());
+        self.preprocess_into(input, &mut rewritten);
+        Cow::Owned(rewritten)
+    }
+
+    /// Rewrite the input according to the lenient options into `out`,
+    /// replacing its contents, so one buffer can serve many inputs
+    pub(crate) fn preprocess_into(&self, input: &str, out: &mut String) {
+        let is_tab_or_newline = |c: char| c == '\t' || c == '\n' || c == '\r';
+        let input = if self.strip_whitespace {
+            input.trim_matches(|c: char| c <= ' ')
+        } else {
+            input
+        };
+        let is_scheme = |scheme: &str, special: &str| {
+            let chars = scheme
+                .chars()
+                .filter(|c| !self.strip_whitespace || !is_tab_or_newline(*c));
+            chars.map(|c| c.to_ascii_lowercase()).eq(special.chars())
+        };
+        let is_special = self.backslash_as_slash
+            && input.split_once(':').is_some_and(|(scheme, _)| {
+                SPECIAL_SCHEMES
+                    .iter()
+                    .any(|special| is_scheme(scheme, special))
+            });
+        out.clear();
+        let mut in_hierarchy = is_special;
+        for c in input.chars() {
+            match c {
+                c if self.strip_whitespace && is_tab_or_newline(c) => (),
+                '\\' if in_hierarchy => out.push('/'),
+                '?' | '#' => {
+                    in_hierarchy = false;
+                    out.push(c);
+                }
+                c => out.push(c),
+            }
+        }
+        if self.empty_port_as_absent {
+            if let Some(authority_start) = out.find("://").map(|i| i + "://".len()) {
+                let authority_end = out[authority_start..]
+                    .find(['/', '?', '#'])
+                    .map_or(out.len(), |i| authority_start + i);
+                if authority_end > authority_start && out[..authority_end].ends_with(':') {
+                    out.remove(authority_end - 1);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{QueryMap, QueryPairs};
+
+    #[test]
+    fn test_strip_whitespace() {
+        let options = ParseOptions::default().strip_whitespace(true);
+        assert_eq!(
+            options.preprocess("\u{0} \thttp://exa\nmple.com/a\r\n b \u{1f}"),
+            "http://example.com/a b"
+        );
+        assert_eq!(
+            ParseOptions::default().preprocess(" http://a.com "),
+            " http://a.com "
+        );
+    }
+
+    #[test]
+    fn test_empty_port_as_absent() {
+        let options = ParseOptions::default().empty_port_as_absent(true);
+        assert_eq!(
+            options.preprocess("http://example.com:/a:b"),
+            "http://example.com/a:b"
+        );
+        assert_eq!(options.preprocess("http://[::1]:"), "http://[::1]");
+        assert_eq!(
+            options.preprocess("http://example.com:80/"),
+            "http://example.com:80/"
+        );
+        assert!("http://example.com:/"
+            .parse::<crate::URI<String>>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_empty_query_pairs_and_keys() {
+        let qs = |s: &str| s.parse::<URI<String>>().unwrap().qs.unwrap();
+        assert_eq!(qs("http://x/?&a=1").len(), 1);
+        assert_eq!(qs("http://x/?a=1&&b=2")["b"], "2");
+        assert_eq!(qs("http://x/?=a")[""], "a");
+        assert!(qs("http://x/?&").is_empty());
+    }
+
+    #[test]
+    fn test_validators_run_in_order() {
+        let one_segment = |uri: &URI<String>| match uri.path.as_deref() {
+            Some([_]) => Ok(()),
+            _ => Err("postgres path must be one segment"),
+        };
+        let options = ParseOptions::default()
+            .validator("POSTGRES", one_segment)
+            .validator("postgres", |_: &URI<String>| Err("always"));
+
+        let uri = "postgres://db/a/b".parse::<URI<String>>().unwrap();
+        assert_eq!(
+            options.validate(&uri),
+            Err("postgres path must be one segment")
+        );
+        let uri = "postgres://db/a".parse::<URI<String>>().unwrap();
+        assert_eq!(options.validate(&uri), Err("always"));
+        let uri = "mysql://db/a/b".parse::<URI<String>>().unwrap();
+        assert_eq!(options.validate(&uri), Ok(()));
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let pairs = || vec![("a", "1"), ("b", "2"), ("a", "3")];
+        let collect = |policy| {
+            ParseOptions::default()
+                .duplicate_keys(policy)
+                .collect_query::<QueryMap<String, String>>(pairs())
+                .map(|qs| qs["a"].clone())
+        };
+        assert_eq!(collect(DuplicateKeys::LastWins).unwrap(), "3");
+        assert_eq!(collect(DuplicateKeys::FirstWins).unwrap(), "1");
+        for policy in [DuplicateKeys::Error, DuplicateKeys::CollectAll] {
+            assert_eq!(
+                collect(policy).unwrap_err().kind(),
+                AurisParseErrorKind::DuplicateQueryKey
+            );
+        }
+
+        let collect_pairs = |policy| {
+            ParseOptions::default()
+                .duplicate_keys(policy)
+                .collect_query::<QueryPairs<String, String>>(pairs())
+                .unwrap()
+        };
+        let owned = |pairs: Vec<(&str, &str)>| -> QueryPairs<String, String> {
+            pairs
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        assert_eq!(collect_pairs(DuplicateKeys::CollectAll), owned(pairs()));
+        assert_eq!(
+            collect_pairs(DuplicateKeys::LastWins),
+            owned(vec![("a", "3"), ("b", "2")])
+        );
+        assert_eq!(
+            collect_pairs(DuplicateKeys::FirstWins),
+            owned(vec![("a", "1"), ("b", "2")])
+        );
+    }
+
+    #[test]
+    fn test_iri_characters() {
+        let options = ParseOptions::default().iri(true);
+        assert!(options
+            .check_iri("http://h/\u{1f600}?\u{e000}#\u{10fffd}x")
+            .is_err());
+        assert!(options.check_iri("http://h/\u{1f600}?\u{e000}#x").is_ok());
+        assert!(options.check_iri("http://h/#a?\u{e000}").is_err());
+        assert_eq!(
+            options.check_iri("http://h/a\u{fffe}").unwrap_err().kind(),
+            AurisParseErrorKind::DisallowedCharacter { offset: 10 }
+        );
+        assert!(options.check_iri("http://h/\u{1fffe}").is_err());
+        assert!(ParseOptions::default()
+            .check_iri("http://h/\u{fffe}")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_strict_ipv4() {
+        let options = ParseOptions::default().strict_ipv4(true);
+        for host in &["0.0.0.0", "255.255.255.255", "example.com", "1e3.1", "123"] {
+            assert!(options.check_ipv4(host).is_ok(), "{}", host);
+        }
+        for host in &[
+            "256.1.1.1",
+            "1.2.3",
+            "1.2.3.4.5",
+            "01.2.3.4",
+            "1..2.3",
+            "1.2.3.4.",
+        ] {
+            assert!(options.check_ipv4(host).is_err(), "{}", host);
+            assert!(ParseOptions::default().check_ipv4(host).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_backslash_as_slash() {
+        let options = ParseOptions::default().backslash_as_slash(true);
+        assert_eq!(
+            options.preprocess(r"HTTPS:\\host\a\b?q=c\d#e\f"),
+            r"HTTPS://host/a/b?q=c\d#e\f"
+        );
+        assert_eq!(options.preprocess(r"custom:\\host\a"), r"custom:\\host\a");
+        assert_eq!(
+            ParseOptions::default().preprocess(r"http:\\host"),
+            r"http:\\host"
+        );
+    }
+}
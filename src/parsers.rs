@@ -1,17 +1,82 @@
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_till},
-    character::complete::{alpha1, digit1},
-    combinator::{all_consuming, cut, opt},
-    multi::many0,
-    sequence::tuple,
-    IResult,
+    bytes::complete::{tag, take_till, take_till1, take_while},
+    character::complete::digit1,
+    combinator::{all_consuming, cut, map, map_res, opt, recognize, verify},
+    error::{context, ErrorKind, ParseError, VerboseError},
+    sequence::{preceded, separated_pair, tuple},
+    Err,
 };
 
-use crate::{Authority, UserInfo, URI};
+use crate::{Authority, PathKind, QueryMap, UserInfo, URI};
 use std::collections::HashMap;
 use std::str;
 
+/// Result of the parsers in this crate, errors carry the context of what was
+/// being parsed when they happened
+pub type IResult<I, O> = nom::IResult<I, O, VerboseError<I>>;
+
+/// Most path segments accepted in a single URI
+pub const MAX_PATH_SEGMENTS: usize = 4096;
+/// Most query pairs (or `;` parameters) accepted in a single URI
+pub const MAX_QUERY_PAIRS: usize = 4096;
+
+/// Like `many0`, but fails with `ErrorKind::TooLarge` instead of collecting
+/// more than `limit` items, so hostile input can't grow the output unbounded.
+fn bounded_many0<'a, O, F>(limit: usize, f: F) -> impl Fn(&'a str) -> IResult<&'a str, Vec<O>>
+where
+    F: Fn(&'a str) -> IResult<&'a str, O>,
+{
+    move |mut i: &'a str| {
+        let mut acc = Vec::with_capacity(4);
+        loop {
+            match f(i) {
+                Err(Err::Error(_)) => return Ok((i, acc)),
+                Err(e) => return Err(e),
+                Ok((remain, o)) => {
+                    // a parser that consumes nothing would loop forever
+                    if remain.len() == i.len() {
+                        return Err(Err::Error(ParseError::from_error_kind(i, ErrorKind::Many0)));
+                    }
+                    if acc.len() == limit {
+                        return Err(Err::Failure(ParseError::from_error_kind(
+                            i,
+                            ErrorKind::TooLarge,
+                        )));
+                    }
+                    i = remain;
+                    acc.push(o);
+                }
+            }
+        }
+    }
+}
+
+/// Label a stage of URI parsing with `context`
+///
+/// With the `tracing` feature each stage also runs in a span, and emits an
+/// event with how much input it consumed or that it failed.
+fn stage<'a, O, F>(name: &'static str, f: F) -> impl Fn(&'a str) -> IResult<&'a str, O>
+where
+    F: Fn(&'a str) -> IResult<&'a str, O>,
+{
+    move |input: &'a str| {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("stage", stage = name, len = input.len()).entered();
+
+        let result = context(name, &f)(input);
+
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok((remaining, _)) => {
+                tracing::trace!(consumed = input.len() - remaining.len(), "parsed")
+            }
+            Err(_) => tracing::trace!("failed"),
+        }
+        result
+    }
+}
+
 /// Parse out the scheme
 ///
 /// # Examples
@@ -34,73 +99,254 @@ pub fn scheme(input: &str) -> IResult<&str, &str> {
     Ok((remaining_post_scheme, scheme_chunk))
 }
 
-fn host_port_combinator<'a>(input: &'a str) -> IResult<&'a str, (&'a str, Option<u16>)> {
-    let port_combinator = |i: &'a str| -> IResult<&str, u16> {
-        let (remain_chunk_1, _) = tag(":")(i)?;
-        let (remain_chunk_2, digits) = digit1(remain_chunk_1)?;
-        Ok((remain_chunk_2, digits.parse::<u16>().unwrap()))
-    };
+/// Parse a scheme followed by a single `:`, for schemes without an authority
+///
+/// # Examples
+///
+/// ```
+/// use auris::parsers;
+/// assert_eq!(parsers::bare_scheme("geo:1,2"), Ok(("1,2", "geo")));
+/// ```
+pub fn bare_scheme(input: &str) -> IResult<&str, &str> {
+    let (remaining, scheme_chunk) = take_till1(|c| c == ':')(input)?;
+    let (remaining_post_scheme, _) = tag(":")(remaining)?;
+    Ok((remaining_post_scheme, scheme_chunk))
+}
+
+/// Whether `scheme` is `ALPHA *( ALPHA / DIGIT / "+" / "-" / "." )`
+pub(crate) fn is_valid_scheme(scheme: &str) -> bool {
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+}
+
+/// Parse out the scheme, noting whether an authority (`//`) follows
+///
+/// Schemes without an authority have to be well formed, so that a
+/// `host:port` isn't taken for a scheme and a path by accident.
+fn scheme_and_hier(input: &str) -> IResult<&str, (&str, bool)> {
+    alt((
+        map(scheme, |s| (s, true)),
+        map(verify(bare_scheme, |s: &str| is_valid_scheme(s)), |s| {
+            (s, false)
+        }),
+    ))(input)
+}
+
+/// Parse the host, including bracketed IP literals
+///
+/// # Examples
+///
+/// ```
+/// use auris::parsers;
+/// assert_eq!(parsers::host("example.com:80"), Ok((":80", "example.com")));
+/// assert_eq!(parsers::host("[2001:db8::7]/c=GB"), Ok(("/c=GB", "[2001:db8::7]")));
+/// ```
+pub fn host(input: &str) -> IResult<&str, &str> {
+    // [2001:db8::7]
+    let ip_literal = recognize(tuple((tag("["), take_till(|c| c == ']'), tag("]"))));
+    // example.com
+    let reg_name =
+        take_till(|c: char| c == '/' || c == '?' || c == '#' || c == ':' || c.is_whitespace());
+    alt((ip_literal, reg_name))(input)
+}
 
-    let domain =
-        |i: &'a str| -> IResult<&'a str, &'a str> { take_till(|c| c == '/' || c == '?')(i) };
+/// Parse a `:1234` port suffix
+///
+/// Once the `:` is seen the port has to be valid.
+pub(crate) fn port(input: &str) -> IResult<&str, u16> {
+    let (remain, _) = tag(":")(input)?;
+    cut(context("port", map_res(digit1, |d: &str| d.parse::<u16>())))(remain)
+}
 
+pub(crate) fn host_port_combinator(input: &str) -> IResult<&str, (&str, Option<u16>)> {
     // asdf.com:1234
-    let (i, host) = domain(input)?;
-    let (i, port) = opt(port_combinator)(i)?;
+    let (i, host) = host(input)?;
+    let (i, port) = opt(port)(i)?;
     Ok((i, (host, port)))
 }
 
 /// Parse the user credentials from the authority section.
-fn authority_credentials<'a>(input: &'a str) -> IResult<&'a str, Option<UserInfo<&'a str>>> {
-    let user_pw_combinator = |i: &'a str| -> IResult<&str, UserInfo<&str>> {
-        // user:pw@
-        let (remain_chunk_1, user) = cut(alpha1)(i)?;
-        let (remain_chunk_2, _) = tag(":")(remain_chunk_1)?;
-        let (remain_chunk_3, password) = cut(alpha1)(remain_chunk_2)?;
-        let (remain_chunk_4, _) = tag("@")(remain_chunk_3)?;
-        Ok((remain_chunk_4, UserInfo::UserAndPassword(user, password)))
+///
+/// The userinfo runs up to the last `@` before the path, as in the WHATWG
+/// URL standard, so `user:p@ss@host` has the password `p@ss`.
+fn authority_credentials(input: &str) -> IResult<&str, Option<UserInfo<&str>>> {
+    let authority_end = input.find(['/', '?', '#']).unwrap_or(input.len());
+    let at = match input[..authority_end].rfind('@') {
+        Some(at) => at,
+        None => return Ok((input, None)),
     };
-
-    // Parse user string without a password
-    let user_combinator = |i: &'a str| -> IResult<&str, UserInfo<&str>> {
-        let (remain_chunk_1, user) = cut(alpha1)(i)?;
-        let (remain_chunk_2, _) = tag("@")(remain_chunk_1)?;
-        Ok((remain_chunk_2, UserInfo::User(user)))
+    let (userinfo, rest) = (&input[..at], &input[at + 1..]);
+    let userinfo = match userinfo.split_once(':') {
+        Some((user, password)) => UserInfo::UserAndPassword(user, password),
+        None => UserInfo::User(userinfo),
     };
-    // The whole statement may fail if there is no match
-    // we flatten this out so that you will just get (None, None)
-    opt(alt((user_pw_combinator, user_combinator)))(input)
+    match userinfo {
+        // the user can't be empty
+        UserInfo::User("") | UserInfo::UserAndPassword("", _) => Ok((input, None)),
+        userinfo => Ok((rest, Some(userinfo))),
+    }
 }
 
 /// Parse the whole path chunk
 pub fn path<'a>(input: &'a str) -> IResult<&'a str, Vec<&'a str>> {
     // Parse a single path chunk
     let path_part = |i: &'a str| -> IResult<&str, &str> {
-        let (remain, (_, chunk)) = tuple((tag("/"), alpha1))(i)?;
+        let (remain, (_, chunk)) = tuple((
+            tag("/"),
+            take_till(|c: char| c == '/' || c == '?' || c == '#' || c.is_whitespace()),
+        ))(i)?;
         Ok((remain, chunk))
     };
     // /a/b/c
-    many0(path_part)(input)
+    bounded_many0(MAX_PATH_SEGMENTS, path_part)(input)
 }
 
-/// Parses ?k=v&k1=v1 into a HashMap
-pub fn query<'a>(input: &'a str) -> IResult<&'a str, HashMap<&'a str, &'a str>> {
+/// Parse the path of a URI without an authority, `/a/b` or `a/b`
+///
+/// # Examples
+///
+/// ```
+/// use auris::{parsers, PathKind};
+/// assert_eq!(
+///     parsers::path_without_authority("jane@example.com?subject=hi"),
+///     Ok(("?subject=hi", (PathKind::Rootless, vec!["jane@example.com"])))
+/// );
+/// assert_eq!(
+///     parsers::path_without_authority("/etc/hosts"),
+///     Ok(("", (PathKind::Absolute, vec!["etc", "hosts"])))
+/// );
+/// ```
+pub fn path_without_authority(input: &str) -> IResult<&str, (PathKind, Vec<&str>)> {
+    if input.starts_with('/') {
+        let (remain, segments) = path(input)?;
+        return Ok((remain, (PathKind::Absolute, segments)));
+    }
+    let (remain, first) =
+        take_till(|c: char| c == '/' || c == '?' || c == '#' || c.is_whitespace())(input)?;
+    let (remain, rest) = path(remain)?;
+    let segments = if first.is_empty() && rest.is_empty() {
+        rest
+    } else {
+        let mut segments = Vec::with_capacity(rest.len() + 1);
+        segments.push(first);
+        segments.extend(rest);
+        segments
+    };
+    Ok((remain, (PathKind::Rootless, segments)))
+}
+
+/// Query pairs in the order they appear
+type QueryPairs<'a> = Vec<(&'a str, &'a str)>;
+
+/// The `&`s of empty query pairs
+fn empty_pairs(input: &str) -> IResult<&str, &str> {
+    take_while(|c: char| c == '&')(input)
+}
+
+/// Parses ?k=v&k1=v1 into its pairs, in order
+///
+/// A key without `=` gets an empty value, and an empty key is kept if it
+/// has one. Empty pairs, as in `?&a=1&&b=2`, are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use auris::parsers;
+/// assert_eq!(
+///     parsers::query_pairs("?a=1&flag&a=2"),
+///     Ok(("", vec![("a", "1"), ("flag", ""), ("a", "2")]))
+/// );
+/// assert_eq!(
+///     parsers::query_pairs("?&=x&&b"),
+///     Ok(("", vec![("", "x"), ("b", "")]))
+/// );
+/// ```
+pub fn query_pairs<'a>(input: &'a str) -> IResult<&'a str, Vec<(&'a str, &'a str)>> {
     let part = |i: &'a str| -> IResult<&str, (&str, &str)> {
-        let (remain, (key, _, value, _)) = tuple((alpha1, tag("="), alpha1, opt(tag("&"))))(i)?;
+        let key_end = |c: char| c == '=' || c == '&' || c == '#' || c.is_whitespace();
+        let value_end = |c: char| c == '&' || c == '#' || c.is_whitespace();
+        let (remain, (pair, _)) = tuple((
+            alt((
+                separated_pair(take_till(key_end), tag("="), take_till(value_end)),
+                map(take_till1(key_end), |key| (key, "")),
+            )),
+            empty_pairs,
+        ))(i)?;
+        Ok((remain, pair))
+    };
+
+    let (post_q, _) = tuple((tag("?"), empty_pairs))(input)?;
+    bounded_many0(MAX_QUERY_PAIRS, part)(post_q)
+}
+
+/// Parses ?k=v&k1=v1 into a [`QueryMap`], the last of repeated keys wins
+///
+/// # Examples
+///
+/// ```
+/// use auris::parsers;
+/// let (_, qs) = parsers::query("?a=1&flag&a=2").unwrap();
+/// assert_eq!(qs.get("a"), Some(&"2"));
+/// assert_eq!(qs.get("flag"), Some(&""));
+/// ```
+pub fn query(input: &str) -> IResult<&str, QueryMap<&str, &str>> {
+    let (remain, vec) = query_pairs(input)?;
+
+    let mut map = QueryMap::default();
+    for (k, v) in vec.into_iter() {
+        map.insert(k, v);
+    }
+    Ok((remain, map))
+}
+
+/// Parses ;k=v;flag parameters into a HashMap
+///
+/// Parameters without a value map to `None`.
+///
+/// # Examples
+///
+/// ```
+/// use auris::parsers;
+/// let (_, params) = parsers::params(";transport=tcp;lr").unwrap();
+/// assert_eq!(params.get("transport"), Some(&Some("tcp")));
+/// assert_eq!(params.get("lr"), Some(&None));
+/// ```
+pub fn params<'a>(input: &'a str) -> IResult<&'a str, HashMap<&'a str, Option<&'a str>>> {
+    let param = |i: &'a str| -> IResult<&str, (&str, Option<&str>)> {
+        let (remain, (_, key, value)) = tuple((
+            tag(";"),
+            take_till1(|c| c == '=' || c == ';' || c == '?' || c == '#'),
+            opt(preceded(
+                tag("="),
+                take_till(|c| c == ';' || c == '?' || c == '#'),
+            )),
+        ))(i)?;
         Ok((remain, (key, value)))
     };
 
-    let (post_q, _) = tag("?")(input)?;
-    let (remain, vec) = many0(part)(post_q)?;
+    let (remain, vec) = bounded_many0(MAX_QUERY_PAIRS, param)(input)?;
 
-    let mut map: HashMap<&str, &str> = HashMap::with_capacity(vec.len());
+    let mut map: HashMap<&str, Option<&str>> = HashMap::with_capacity(vec.len());
     for (k, v) in vec.into_iter() {
         map.insert(k, v);
     }
-    //vec.into_iter().map(|(k, v)| map.entry(k).or_insert(v));
     Ok((remain, map))
 }
 
+/// Parses the #fragment
+///
+/// # Examples
+///
+/// ```
+/// use auris::parsers;
+/// assert_eq!(parsers::fragment("#nose"), Ok(("", "nose")));
+/// ```
+pub fn fragment(input: &str) -> IResult<&str, &str> {
+    preceded(tag("#"), take_till(|c: char| c.is_whitespace()))(input)
+}
+
 /// Parses the authority section of the URI
 ///
 /// # Examples
@@ -138,35 +384,99 @@ pub fn authority(input: &str) -> IResult<&str, Authority<&str>> {
 ///
 /// ```
 /// use auris::parsers;
-/// parsers::uri("scheme://user:pw@host.pizza/path1/path2/?k=v&k1=v1");
+/// parsers::uri("scheme://user:pw@host.pizza/path1/path2/?k=v&k1=v1#fragment");
 /// ```
 pub fn uri(input: &str) -> IResult<&str, URI<&str>> {
-    let (i, scheme) = scheme(input)?;
-    let (i, userinfo) = authority_credentials(i)?;
-    let (i, (host, port)) = host_port_combinator(i)?;
-    let (i, path) = path(i)?;
-    let (i, query) = opt(query)(i)?;
+    let (i, (mut uri, pairs)) = uri_with_query_pairs(input)?;
+    uri.qs = pairs.map(|pairs| pairs.into_iter().collect());
+    Ok((i, uri))
+}
+
+/// Parses a full URI, leaving the query as its pairs in order
+///
+/// The `qs` of the returned URI is always `None`.
+pub(crate) fn uri_with_query_pairs(
+    input: &str,
+) -> IResult<&str, (URI<&str>, Option<QueryPairs<'_>>)> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("auris::uri", len = input.len()).entered();
+
+    let (i, (scheme, has_authority)) = stage("scheme", scheme_and_hier)(input)?;
+    let (i, (userinfo, (host, port)), (path_kind, path)) = if has_authority {
+        let (i, authority) = stage(
+            "authority",
+            tuple((authority_credentials, host_port_combinator)),
+        )(i)?;
+        let (i, path) = stage("path", path)(i)?;
+        (i, authority, (PathKind::AfterAuthority, path))
+    } else {
+        let (i, (path_kind, path)) = stage("path", path_without_authority)(i)?;
+        // `file:/etc/hosts` is a common short form of `file:///etc/hosts`
+        let path_kind = match path_kind {
+            PathKind::Absolute if scheme.eq_ignore_ascii_case("file") => PathKind::AfterAuthority,
+            kind => kind,
+        };
+        (i, (None, ("", None)), (path_kind, path))
+    };
+    let (i, query) = stage("query", opt(query_pairs))(i)?;
+    let (i, fragment) = stage("fragment", opt(fragment))(i)?;
 
     Ok((
         i,
-        URI {
-            scheme,
-            authority: Authority {
-                host,
-                userinfo,
-                port,
+        (
+            URI {
+                scheme,
+                authority: Authority {
+                    host,
+                    userinfo,
+                    port,
+                },
+                path: Some(path),
+                path_kind,
+                qs: None,
+                fragment,
             },
-            path: Some(path),
-            qs: query,
-        },
+            query,
+        ),
     ))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::URI;
+
+    #[test]
+    fn test_arbitrary_input_never_panics() {
+        // every string of up to three pieces, covering delimiters, broken
+        // escapes, brackets and multibyte characters
+        let pieces = [
+            "", "a", "1", ":", "/", "//", "?", "#", "@", "[", "]", "%", "%4", "%e2", "=", "&", "é",
+            "\\", "0x", ".", " ", "::1", "xn--", "file",
+        ];
+        for a in &pieces {
+            for b in &pieces {
+                for c in &pieces {
+                    let input = format!("{}{}{}", a, b, c);
+                    let _ = uri(&input);
+                    if let Ok(parsed) = input.parse::<URI<String>>() {
+                        let displayed = parsed.to_string();
+                        let reparsed = displayed.parse::<URI<String>>();
+                        assert_eq!(
+                            reparsed.ok(),
+                            Some(parsed),
+                            "{:?} as {:?}",
+                            input,
+                            displayed
+                        );
+                    }
+                }
+            }
+        }
+    }
 
     #[test]
+    #[allow(clippy::useless_asref)]
     fn test_authority() {
         assert_eq!(
             authority("bob:bob@bob"),
@@ -193,6 +503,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::useless_asref)]
     fn test_user_info() {
         assert_eq!(
             authority_credentials("bob:password@host"),
@@ -206,6 +517,29 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_user_info_with_digits() {
+        assert_eq!(
+            authority_credentials("proxy1:s3cret@10.0.0.1"),
+            Ok((
+                "10.0.0.1",
+                Some(UserInfo::UserAndPassword("proxy1", "s3cret"))
+            ))
+        );
+        assert_eq!(authority_credentials("10.0.0.1"), Ok(("10.0.0.1", None)))
+    }
+
+    #[test]
+    fn test_user_info_splits_at_last_at() {
+        assert_eq!(
+            authority_credentials("user:p@ss@host/a@b"),
+            Ok(("host/a@b", Some(UserInfo::UserAndPassword("user", "p@ss"))))
+        );
+        assert_eq!(authority_credentials("host/a@b"), Ok(("host/a@b", None)));
+        let (_, parsed) = uri("http://user:p@ss@host/").unwrap();
+        assert_eq!(parsed.authority.host, "host");
+    }
+
     #[test]
     fn test_bad_user_info() {
         assert_eq!(
@@ -215,6 +549,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::useless_asref)]
     fn test_path() {
         let matched_path = vec!["f", "g", "h"];
         assert_eq!(
@@ -227,6 +562,53 @@ mod test {
     }
 
     #[test]
+    fn test_invalid_port_is_an_error() {
+        assert!(uri("a://b:x/").is_err());
+        assert!(uri("a://b:65536/").is_err());
+        assert_eq!(authority("b:65535").map(|(_, a)| a.port), Ok(Some(65535)));
+    }
+
+    #[test]
+    fn test_path_segment_limit() {
+        let at_limit = "/a".repeat(MAX_PATH_SEGMENTS);
+        assert_eq!(path(&at_limit).map(|(_, p)| p.len()), Ok(MAX_PATH_SEGMENTS));
+
+        let over_limit = "/a".repeat(MAX_PATH_SEGMENTS + 1);
+        assert!(path(&over_limit).is_err());
+        assert!(uri(&format!("s://h{}", over_limit)).is_err());
+    }
+
+    #[test]
+    fn test_query_pair_limit() {
+        let over_limit = format!("?{}", "a=b&".repeat(MAX_QUERY_PAIRS + 1));
+        assert!(query(&over_limit).is_err());
+    }
+
+    #[test]
+    fn test_uri_without_authority() {
+        let (_, mailto) = uri("mailto:jane@example.com?subject=hi").unwrap();
+        assert_eq!(mailto.path_kind, PathKind::Rootless);
+        assert_eq!(mailto.path, Some(vec!["jane@example.com"]));
+        assert_eq!(mailto.authority.host, "");
+
+        let (_, news) = uri("news:/comp.lang.rust").unwrap();
+        assert_eq!(news.path_kind, PathKind::Absolute);
+        assert_eq!(news.path, Some(vec!["comp.lang.rust"]));
+
+        assert!(uri("1http:foo").is_err());
+        assert!(uri("a b:foo").is_err());
+    }
+
+    #[test]
+    fn test_single_slash_file_uri() {
+        let (_, short) = uri("file:/etc/hosts").unwrap();
+        let (_, long) = uri("file:///etc/hosts").unwrap();
+        assert_eq!(short, long);
+        assert_eq!(short.path_kind, PathKind::AfterAuthority);
+    }
+
+    #[test]
+    #[allow(clippy::useless_asref)]
     fn test_full_absolute_uri() {
         let query_string_map = [("i".as_ref(), "j".as_ref()), ("k".as_ref(), "l".as_ref())]
             .iter()
@@ -245,7 +627,9 @@ mod test {
                         port: None
                     },
                     path: Some(vec!("f".as_ref(), "g".as_ref(), "h".as_ref())),
-                    qs: Some(query_string_map)
+                    path_kind: PathKind::AfterAuthority,
+                    qs: Some(query_string_map),
+                    fragment: None,
                 }
             ))
         )
@@ -0,0 +1,433 @@
+//! Building up paths
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::URI;
+//!
+//! let base = "https://api.example.com/".parse::<URI<String>>().unwrap();
+//! let uri = base / "v1" / "users" / "jane doe";
+//!
+//! assert_eq!("https://api.example.com/v1/users/jane%20doe", format!("{}", uri));
+//! ```
+use crate::percent;
+use crate::{AurisParseErrorKind, ParseError, PathKind, URI};
+use std::borrow::Cow;
+use std::ops::{Div, DivAssign};
+
+/// The first path segment of well-known URIs (RFC 8615)
+const WELL_KNOWN: &str = ".well-known";
+
+/// What [`URI::remove_dot_segments`] does with `..` segments that would
+/// climb above the root
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum DotDotPolicy {
+    /// Drop them, as browsers and RFC 3986 do, `/../a` becomes `/a`
+    #[default]
+    Drop,
+    /// Keep them at the start of the path, `/../a` stays as it is
+    Keep,
+    /// Fail with [`AurisParseErrorKind::PathEscapesRoot`]
+    Error,
+}
+
+impl URI<String> {
+    /// Remove `.` and `..` segments from the path (RFC 3986 section 5.2.4)
+    ///
+    /// Escaped dots such as `%2e%2e` count as dot segments as well. A path
+    /// ending in a dot segment keeps a trailing slash. `policy` decides what
+    /// happens to a `..` with no segment left to remove, on error the URI
+    /// is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::path::DotDotPolicy;
+    /// use auris::URI;
+    ///
+    /// let mut uri = "http://h/a/./b/../../../c".parse::<URI<String>>().unwrap();
+    /// assert!(uri.clone().remove_dot_segments(DotDotPolicy::Error).is_err());
+    ///
+    /// let mut kept = uri.clone();
+    /// kept.remove_dot_segments(DotDotPolicy::Keep).unwrap();
+    /// assert_eq!("http://h/../c", format!("{}", kept));
+    ///
+    /// uri.remove_dot_segments(DotDotPolicy::Drop).unwrap();
+    /// assert_eq!("http://h/c", format!("{}", uri));
+    /// ```
+    pub fn remove_dot_segments(&mut self, policy: DotDotPolicy) -> Result<(), ParseError> {
+        let segments = match self.path.as_ref() {
+            Some(segments) => segments,
+            None => return Ok(()),
+        };
+        let mut output: Vec<String> = Vec::with_capacity(segments.len());
+        let mut trailing_slash = false;
+        for segment in segments {
+            trailing_slash = true;
+            match &*percent::decode(segment) {
+                "." => (),
+                ".." => match output.last().map(String::as_str) {
+                    Some(last) if last != ".." || policy != DotDotPolicy::Keep => {
+                        output.pop();
+                    }
+                    _ => match policy {
+                        DotDotPolicy::Drop => (),
+                        DotDotPolicy::Keep => output.push("..".to_string()),
+                        DotDotPolicy::Error => {
+                            return Err(AurisParseErrorKind::PathEscapesRoot.into())
+                        }
+                    },
+                },
+                _ => {
+                    trailing_slash = false;
+                    output.push(segment.clone());
+                }
+            }
+        }
+        if trailing_slash {
+            output.push(String::new());
+        }
+        self.path = Some(output);
+        Ok(())
+    }
+
+    /// A copy with just the scheme and authority, and an empty path
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "https://api.example.com:8443/v1/users?page=2#top"
+    ///     .parse::<URI<String>>()
+    ///     .unwrap();
+    ///
+    /// assert_eq!("https://api.example.com:8443/", format!("{}", uri.base()));
+    /// ```
+    pub fn base(&self) -> URI<String> {
+        URI {
+            scheme: self.scheme.clone(),
+            authority: self.authority.clone(),
+            path: Some(vec![String::new()]),
+            path_kind: self.path_kind,
+            qs: None,
+            fragment: None,
+        }
+    }
+
+    /// The well-known URI `suffix` of this URI's origin, as RFC 8615
+    /// defines, such as `https://example.com/.well-known/change-password`
+    ///
+    /// The path, query and fragment are replaced. A `/` in `suffix` separates
+    /// segments, which are encoded as [`Div`] does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let site = "https://example.com/account/settings?tab=2".parse::<URI<String>>().unwrap();
+    ///
+    /// let change_password = site.well_known("change-password");
+    /// assert_eq!("https://example.com/.well-known/change-password", format!("{}", change_password));
+    /// assert_eq!(change_password.well_known_suffix(), Some("change-password"));
+    ///
+    /// let challenge = site.well_known("acme-challenge/abc");
+    /// assert_eq!("https://example.com/.well-known/acme-challenge/abc", format!("{}", challenge));
+    /// ```
+    pub fn well_known(&self, suffix: &str) -> URI<String> {
+        suffix
+            .split('/')
+            .fold(self.base() / WELL_KNOWN, |uri, segment| uri / segment)
+    }
+
+    /// The registered name after `/.well-known/`, if this is a well-known
+    /// URI
+    pub fn well_known_suffix(&self) -> Option<&str> {
+        match self.path.as_deref() {
+            Some([prefix, suffix, ..]) if prefix == WELL_KNOWN && !suffix.is_empty() => {
+                Some(suffix)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the path is under `/.well-known/`, see
+    /// [`URI::well_known`]
+    pub fn is_well_known(&self) -> bool {
+        self.has_authority() && self.well_known_suffix().is_some()
+    }
+
+    /// Whether the decoded path climbs above the root with `..` segments,
+    /// as `/a/../../etc/passwd` does
+    ///
+    /// Segments are percent decoded first and split again on `/` and `\`,
+    /// so `%2e%2e` and `..%2f..` are caught too. Services mapping paths onto
+    /// a filesystem should reject such URIs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let escapes = |s: &str| s.parse::<URI<String>>().unwrap().path_escapes_root();
+    ///
+    /// assert!(escapes("http://h/a/../../etc/passwd"));
+    /// assert!(escapes("http://h/static/%2e%2e/%2E%2E/secret"));
+    /// assert!(!escapes("http://h/a/b/../c"));
+    /// ```
+    pub fn path_escapes_root(&self) -> bool {
+        let mut depth: usize = 0;
+        for segment in self.path.iter().flatten() {
+            for part in percent::decode(segment).split(['/', '\\']) {
+                match part {
+                    "" | "." => (),
+                    ".." => match depth.checked_sub(1) {
+                        Some(d) => depth = d,
+                        None => return true,
+                    },
+                    _ => depth += 1,
+                }
+            }
+        }
+        false
+    }
+
+    /// The path segments with their `%XX` escapes decoded, for mapping
+    /// paths onto a filesystem
+    ///
+    /// `path` keeps the segments as written, so the URI displays
+    /// unchanged. Segments without escapes are borrowed. Fails if a
+    /// segment doesn't decode to UTF-8, rather than replacing bytes so two
+    /// names end up the same. A decoded segment may contain `/` or be
+    /// `..`, check for those before using it as a file name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "file:///srv/path%20with%20spaces".parse::<URI<String>>().unwrap();
+    /// assert_eq!(uri.decoded_path().unwrap(), vec!["srv", "path with spaces"]);
+    /// assert_eq!("file:///srv/path%20with%20spaces", format!("{}", uri));
+    ///
+    /// let uri = "file:///srv/%FF".parse::<URI<String>>().unwrap();
+    /// assert!(uri.decoded_path().is_err());
+    /// ```
+    pub fn decoded_path(&self) -> Result<Vec<Cow<'_, str>>, ParseError> {
+        self.path
+            .iter()
+            .flatten()
+            .map(|segment| match percent::decode_bytes(segment) {
+                Cow::Borrowed(_) => Ok(Cow::Borrowed(segment.as_str())),
+                Cow::Owned(bytes) => String::from_utf8(bytes)
+                    .map(Cow::Owned)
+                    .map_err(|_| AurisParseErrorKind::Rejected("path segment is not UTF-8").into()),
+            })
+            .collect()
+    }
+
+    /// The path with each segment percent encoded, for a path whose
+    /// segments were set decoded
+    ///
+    /// Escapes are only added where a segment needs them, so a `%` already
+    /// in a segment is escaped as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let mut uri = "file:///srv/a".parse::<URI<String>>().unwrap();
+    /// uri.path = Some(vec!["srv".to_string(), "50% off/sale".to_string()]);
+    /// assert_eq!(uri.encoded_path_str(), "/srv/50%25%20off%2Fsale");
+    /// ```
+    pub fn encoded_path_str(&self) -> String {
+        let mut path = String::new();
+        for (i, segment) in self.path.iter().flatten().enumerate() {
+            if i > 0 || self.path_kind != PathKind::Rootless {
+                path.push('/');
+            }
+            path.push_str(&percent::encode_path_segment(segment));
+        }
+        path
+    }
+
+    /// A copy with the last path segment removed, `None` at the root
+    ///
+    /// A trailing slash is kept, so `/a/b/` has the parent `/a/` while `/a/b`
+    /// has the parent `/a`. The query and fragment belong to the original
+    /// resource and are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "https://example.com/docs/api/?v=2".parse::<URI<String>>().unwrap();
+    /// let parent = uri.parent().unwrap();
+    /// assert_eq!("https://example.com/docs/", format!("{}", parent));
+    ///
+    /// let root = parent.parent().unwrap();
+    /// assert_eq!("https://example.com/", format!("{}", root));
+    /// assert!(root.parent().is_none());
+    /// ```
+    pub fn parent(&self) -> Option<URI<String>> {
+        let mut path = self.path.clone().unwrap_or_default();
+        let trailing_slash = path.last().is_some_and(|last| last.is_empty());
+        if trailing_slash {
+            path.pop();
+        }
+        path.pop()?;
+        if trailing_slash || path.is_empty() {
+            path.push(String::new());
+        }
+        Some(URI {
+            path: Some(path),
+            qs: None,
+            fragment: None,
+            ..self.clone()
+        })
+    }
+}
+
+/// Appends `segment` to the path as a single, encoded segment
+///
+/// A `/` in `segment` is encoded too, so it can't add more than one segment,
+/// and a `.` or `..` segment is encoded as `%2E` or `%2E%2E`, so it can't
+/// climb out of the path it is appended to. An empty last segment, as left
+/// by a trailing slash, is replaced.
+///
+/// [`URI::canonicalize`] decodes `%2E` and then removes dot segments, so
+/// check [`URI::path_escapes_root`] before canonicalizing a path built
+/// from untrusted input.
+impl DivAssign<&str> for URI<String> {
+    fn div_assign(&mut self, segment: &str) {
+        let path = self.path.get_or_insert_with(Vec::new);
+        if path.last().is_some_and(|last| last.is_empty()) {
+            path.pop();
+        }
+        let encoded = match segment {
+            "." => "%2E".to_string(),
+            ".." => "%2E%2E".to_string(),
+            segment => percent::encode_path_segment(segment).into_owned(),
+        };
+        path.push(encoded);
+    }
+}
+
+/// Appends `segment` to the path, see the `DivAssign` implementation
+impl Div<&str> for URI<String> {
+    type Output = URI<String>;
+
+    fn div(mut self, segment: &str) -> Self::Output {
+        self /= segment;
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decoded_path() {
+        let uri = "http://h/a%2Fb/%2e%2E/%7E/x%3Fy"
+            .parse::<URI<String>>()
+            .unwrap();
+        assert_eq!(uri.decoded_path().unwrap(), vec!["a/b", "..", "~", "x?y"]);
+        assert_eq!(uri.path.as_ref().unwrap()[3], "x%3Fy");
+        assert_eq!("http://h/a%2Fb/%2e%2E/%7E/x%3Fy", uri.to_string());
+        assert!(matches!(uri.decoded_path().unwrap()[2], Cow::Owned(_)));
+
+        let bare = "mailto:".parse::<URI<String>>().unwrap();
+        assert!(bare.decoded_path().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parent() {
+        let parent = |uri: &str| {
+            uri.parse::<URI<String>>()
+                .unwrap()
+                .parent()
+                .map(|p| p.to_string())
+        };
+        assert_eq!(parent("http://h/a/b"), Some("http://h/a".to_string()));
+        assert_eq!(parent("http://h/a"), Some("http://h/".to_string()));
+        assert_eq!(parent("http://h/"), None);
+        assert_eq!(parent("http://h"), None);
+        assert_eq!(parent("urn:a/b"), Some("urn:a".to_string()));
+    }
+
+    #[test]
+    fn test_well_known() {
+        let well_known = |s: &str| s.parse::<URI<String>>().unwrap().is_well_known();
+        assert!(well_known("https://h/.well-known/security.txt"));
+        assert!(well_known("https://h/.well-known/acme-challenge/x"));
+        assert!(!well_known("https://h/.well-known/"));
+        assert!(!well_known("https://h/.well-known"));
+        assert!(!well_known("https://h/a/.well-known/x"));
+        assert!(!well_known("https://h/.Well-Known/x"));
+
+        let uri = "http://h:8080".parse::<URI<String>>().unwrap();
+        assert_eq!(
+            uri.well_known("a b").to_string(),
+            "http://h:8080/.well-known/a%20b"
+        );
+    }
+
+    #[test]
+    fn test_path_escapes_root_with_encoded_separators() {
+        let escapes = |s: &str| s.parse::<URI<String>>().unwrap().path_escapes_root();
+        assert!(escapes("http://h/a/..%2f..%2fetc"));
+        assert!(escapes("http://h/a%5c..%5c..%5cwin.ini"));
+        assert!(!escapes("http://h/a/..%2fb"));
+        assert!(!escapes("http://h/..a/b.."));
+    }
+
+    #[test]
+    fn test_remove_dot_segments() {
+        let remove = |s: &str, policy| {
+            let mut uri = s.parse::<URI<String>>().unwrap();
+            uri.remove_dot_segments(policy).map(|_| uri.to_string())
+        };
+        assert_eq!(
+            remove("http://h/a/b/..", DotDotPolicy::Drop).unwrap(),
+            "http://h/a/"
+        );
+        assert_eq!(
+            remove("http://h/a/%2E%2e/b/.", DotDotPolicy::Drop).unwrap(),
+            "http://h/b/"
+        );
+        assert_eq!(
+            remove("http://h/../..", DotDotPolicy::Drop).unwrap(),
+            "http://h/"
+        );
+        assert_eq!(
+            remove("http://h/../../a", DotDotPolicy::Keep).unwrap(),
+            "http://h/../../a"
+        );
+        assert_eq!(
+            remove("http://h/a?q=1", DotDotPolicy::Error).unwrap(),
+            "http://h/a?q=1"
+        );
+        assert_eq!(
+            remove("http://h/%2e%2e", DotDotPolicy::Error)
+                .unwrap_err()
+                .kind(),
+            AurisParseErrorKind::PathEscapesRoot
+        );
+    }
+
+    #[test]
+    fn test_div() {
+        let mut uri = "http://h/a/?q=1".parse::<URI<String>>().unwrap() / "b/c";
+        assert_eq!(uri.path, Some(vec!["a".to_string(), "b%2Fc".to_string()]));
+        uri /= "..";
+        assert_eq!(uri.to_string(), "http://h/a/b%2Fc/%2E%2E?q=1");
+        uri /= ".";
+        assert_eq!(uri.to_string(), "http://h/a/b%2Fc/%2E%2E/%2E?q=1");
+        let uri = "http://h/a".parse::<URI<String>>().unwrap() / "..." / ".x";
+        assert_eq!(uri.to_string(), "http://h/a/.../.x");
+    }
+}
@@ -0,0 +1,362 @@
+//! Percent encoding and decoding
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::percent;
+//!
+//! assert_eq!(percent::decode("caf%C3%A9%20au%20lait"), "café au lait");
+//! assert_eq!(percent::encode_path_segment("50% off/sale"), "50%25%20off%2Fsale");
+//! assert_eq!(percent::encode("a&b=c d", &percent::EncodeSet::QUERY), "a%26b%3Dc%20d");
+//! ```
+use crate::charset::Charset;
+use std::borrow::Cow;
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Build the table of ASCII bytes left unescaped
+const fn table(chars: &[u8]) -> u128 {
+    let mut keep = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        keep |= 1 << chars[i];
+        i += 1;
+    }
+    keep
+}
+
+const ALPHANUMERIC: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// The characters [`encode`] leaves unescaped
+///
+/// Start from one of the presets, and use [`EncodeSet::builder`] when an
+/// API wants more (or fewer) characters escaped than RFC 3986 requires.
+/// Non-ASCII characters are always escaped, and so is `%` unless
+/// [`EncodeSetBuilder::preserve_escapes`] is set.
+///
+/// # Examples
+///
+/// ```
+/// use auris::percent::{self, EncodeSet};
+///
+/// let strict = EncodeSet::builder(EncodeSet::QUERY).encode("!'()*").keep("[]").build();
+///
+/// assert_eq!(percent::encode("a(1)[2]", &EncodeSet::QUERY), "a(1)%5B2%5D");
+/// assert_eq!(percent::encode("a(1)[2]", &strict), "a%281%29[2]");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EncodeSet {
+    keep: u128,
+    preserve_escapes: bool,
+}
+
+impl EncodeSet {
+    /// Only the unreserved characters, letters, digits and `-._~`
+    pub const UNRESERVED: EncodeSet = EncodeSet {
+        keep: table(ALPHANUMERIC) | table(b"-._~"),
+        preserve_escapes: false,
+    };
+
+    /// A single path segment, everything `pchar` allows, `/` is escaped
+    pub const PATH_SEGMENT: EncodeSet = EncodeSet {
+        keep: EncodeSet::UNRESERVED.keep | table(b"!$&'()*+,;=:@"),
+        preserve_escapes: false,
+    };
+
+    /// A whole path, like [`EncodeSet::PATH_SEGMENT`] but keeping `/`
+    pub const PATH: EncodeSet = EncodeSet {
+        keep: EncodeSet::PATH_SEGMENT.keep | table(b"/"),
+        preserve_escapes: false,
+    };
+
+    /// A query key or value, `&`, `=`, `+` and `#` are escaped
+    pub const QUERY: EncodeSet = EncodeSet {
+        keep: EncodeSet::UNRESERVED.keep | table(b"!$'()*,;:@/?"),
+        preserve_escapes: false,
+    };
+
+    /// A user name or password, `:` and `@` are escaped
+    pub const USERINFO: EncodeSet = EncodeSet {
+        keep: EncodeSet::UNRESERVED.keep | table(b"!$&'()*+,;="),
+        preserve_escapes: false,
+    };
+
+    /// Customize a preset
+    pub fn builder(preset: EncodeSet) -> EncodeSetBuilder {
+        EncodeSetBuilder { set: preset }
+    }
+
+    /// Whether `b` is left as is
+    pub fn keeps(&self, b: u8) -> bool {
+        b < 128 && self.keep & 1 << b != 0
+    }
+}
+
+/// Adds or removes characters from an [`EncodeSet`]
+#[derive(Debug, Clone)]
+pub struct EncodeSetBuilder {
+    set: EncodeSet,
+}
+
+impl EncodeSetBuilder {
+    /// Escape the characters in `chars` as well
+    pub fn encode(mut self, chars: &str) -> Self {
+        for b in chars.bytes().filter(u8::is_ascii) {
+            self.set.keep &= !(1 << b);
+        }
+        self
+    }
+
+    /// Leave the characters in `chars` unescaped
+    ///
+    /// Non-ASCII characters and `%` are ignored, they are always escaped.
+    pub fn keep(mut self, chars: &str) -> Self {
+        for b in chars.bytes().filter(|b| b.is_ascii() && *b != b'%') {
+            self.set.keep |= 1 << b;
+        }
+        self
+    }
+
+    /// Leave `%` alone when it starts a valid escape
+    ///
+    /// Encoding a string that may already be encoded then doesn't turn
+    /// `%20` into `%2520`. A `%` that isn't followed by two hex digits is
+    /// still escaped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::percent::{self, EncodeSet};
+    ///
+    /// let set = EncodeSet::builder(EncodeSet::PATH).preserve_escapes(true).build();
+    ///
+    /// assert_eq!(percent::encode("a%20b c%", &set), "a%20b%20c%25");
+    /// assert_eq!(percent::encode(&percent::encode("a b", &set), &set), "a%20b");
+    /// ```
+    pub fn preserve_escapes(mut self, preserve: bool) -> Self {
+        self.set.preserve_escapes = preserve;
+        self
+    }
+
+    pub fn build(self) -> EncodeSet {
+        self.set
+    }
+}
+
+/// Escape every byte of `input` that `set` doesn't keep as `%XX`
+pub fn encode<'a>(input: &'a str, set: &EncodeSet) -> Cow<'a, str> {
+    let bytes = input.as_bytes();
+    let is_escape = |i: usize| {
+        set.preserve_escapes
+            && bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && hex_value(bytes[i + 1]).is_some()
+            && hex_value(bytes[i + 2]).is_some()
+    };
+    let kept = |i: usize| set.keeps(bytes[i]) || is_escape(i);
+    if (0..bytes.len()).all(kept) {
+        return Cow::Borrowed(input);
+    }
+    let mut encoded = String::with_capacity(input.len() + 8);
+    for (i, b) in bytes.iter().enumerate() {
+        if kept(i) {
+            encoded.push(char::from(*b));
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    Cow::Owned(encoded)
+}
+
+/// Escape the non-ASCII characters of `input`, leaving the rest as is
+pub(crate) fn encode_non_ascii(input: &str) -> Cow<'_, str> {
+    if input.is_ascii() {
+        return Cow::Borrowed(input);
+    }
+    let mut encoded = String::with_capacity(input.len() * 2);
+    for c in input.chars() {
+        if c.is_ascii() {
+            encoded.push(c);
+        } else {
+            let mut buf = [0; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                encoded.push_str(&format!("%{:02X}", b));
+            }
+        }
+    }
+    Cow::Owned(encoded)
+}
+
+/// Encode `input` for use as a single path segment
+///
+/// Everything but the characters RFC 3986 allows in a segment is escaped,
+/// including `/` and `%`.
+pub fn encode_path_segment(input: &str) -> Cow<'_, str> {
+    encode(input, &EncodeSet::PATH_SEGMENT)
+}
+
+/// Decode escaped unreserved characters and uppercase the hex digits of
+/// the other escapes, as RFC 3986 section 6.2.2 describes
+///
+/// `%7e%2f` becomes `~%2F`, malformed escapes are kept as is.
+pub(crate) fn normalize_escapes(input: &str) -> Cow<'_, str> {
+    if !input.contains('%') {
+        return Cow::Borrowed(input);
+    }
+    let bytes = input.as_bytes();
+    let mut normalized = String::with_capacity(input.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                let b = hi << 4 | lo;
+                if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                    normalized.push(char::from(b));
+                } else {
+                    normalized.push_str(&format!("%{:02X}", b));
+                }
+                i += 3;
+                continue;
+            }
+        }
+        if bytes[i] == b'%' {
+            normalized.push('%');
+            i += 1;
+            continue;
+        }
+        // copy up to the next escape, which is always on a char boundary
+        let next = input[i..].find('%').map_or(input.len(), |n| i + n);
+        normalized.push_str(&input[i..next]);
+        i = next;
+    }
+    Cow::Owned(normalized)
+}
+
+/// Decode `%XX` escapes into raw bytes
+///
+/// Malformed escapes such as `%G1` or a trailing `%` are kept as is.
+pub fn decode_bytes(input: &str) -> Cow<'_, [u8]> {
+    let bytes = input.as_bytes();
+    if !bytes.contains(&b'%') {
+        return Cow::Borrowed(bytes);
+    }
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                decoded.push(hi << 4 | lo);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    Cow::Owned(decoded)
+}
+
+/// Decode `%XX` escapes as UTF-8
+///
+/// Invalid UTF-8 sequences are replaced with `U+FFFD`.
+pub fn decode(input: &str) -> Cow<'_, str> {
+    match decode_bytes(input) {
+        Cow::Borrowed(_) => Cow::Borrowed(input),
+        Cow::Owned(bytes) => Cow::Owned(String::from_utf8_lossy(&bytes).into_owned()),
+    }
+}
+
+/// Decode `%XX` escapes, interpreting the bytes with `charset`
+///
+/// # Examples
+///
+/// ```
+/// use auris::charset::Windows1252;
+/// use auris::percent;
+///
+/// assert_eq!(percent::decode_with("%93quoted%94", &Windows1252), "\u{201c}quoted\u{201d}");
+/// ```
+pub fn decode_with<C>(input: &str, charset: &C) -> String
+where
+    C: Charset + ?Sized,
+{
+    charset.decode(&decode_bytes(input))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_bytes() {
+        assert_eq!(decode_bytes("a%20b"), Cow::Owned::<[u8]>(b"a b".to_vec()));
+        assert_eq!(decode_bytes("plain"), Cow::Borrowed(&b"plain"[..]));
+        assert_eq!(&*decode_bytes("%e9%"), &[0xe9, b'%'][..]);
+        assert_eq!(&*decode_bytes("%zz%4"), &b"%zz%4"[..]);
+    }
+
+    #[test]
+    fn test_encode_path_segment() {
+        assert_eq!(encode_path_segment("a:b@c"), Cow::Borrowed("a:b@c"));
+        assert_eq!(encode_path_segment("é?#"), "%C3%A9%3F%23");
+    }
+
+    #[test]
+    fn test_encode_set_presets() {
+        let input = "a b/c?d&e=f+g:h@i#";
+        assert_eq!(
+            encode(input, &EncodeSet::UNRESERVED),
+            "a%20b%2Fc%3Fd%26e%3Df%2Bg%3Ah%40i%23"
+        );
+        assert_eq!(encode(input, &EncodeSet::PATH), "a%20b/c%3Fd&e=f+g:h@i%23");
+        assert_eq!(
+            encode(input, &EncodeSet::QUERY),
+            "a%20b/c?d%26e%3Df%2Bg:h@i%23"
+        );
+        assert_eq!(
+            encode(input, &EncodeSet::USERINFO),
+            "a%20b%2Fc%3Fd&e=f+g%3Ah%40i%23"
+        );
+    }
+
+    #[test]
+    fn test_encode_set_builder() {
+        let set = EncodeSet::builder(EncodeSet::PATH_SEGMENT)
+            .encode("+é")
+            .keep("%/é")
+            .build();
+        assert!(!set.keeps(b'+'));
+        assert!(set.keeps(b'/'));
+        assert!(!set.keeps(b'%'));
+        assert_eq!(encode("1+1/é%", &set), "1%2B1/%C3%A9%25");
+    }
+
+    #[test]
+    fn test_encode_preserving_escapes() {
+        let set = EncodeSet::builder(EncodeSet::QUERY)
+            .preserve_escapes(true)
+            .build();
+        assert_eq!(encode("100%25", &set), Cow::Borrowed("100%25"));
+        assert_eq!(encode("%zz%4%", &set), "%25zz%254%25");
+        assert_eq!(encode("%41%", &set), "%41%25");
+        assert_eq!(encode("%41", &EncodeSet::QUERY), "%2541");
+    }
+
+    #[test]
+    fn test_normalize_escapes() {
+        assert_eq!(normalize_escapes("%7e%2f%41é%zz%"), "~%2FAé%zz%");
+        assert_eq!(normalize_escapes("plain"), Cow::Borrowed("plain"));
+    }
+
+    #[test]
+    fn test_decode_invalid_utf8() {
+        assert_eq!(decode("%FFok"), "\u{fffd}ok");
+    }
+}
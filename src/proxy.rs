@@ -0,0 +1,185 @@
+//! Helpers for the `http_proxy`, `https_proxy` and `no_proxy` environment
+//! variables
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::URI;
+//! use auris::proxy::{self, NoProxy};
+//!
+//! let proxy = proxy::parse_proxy_url("user:pw@proxy.internal:3128").unwrap();
+//! assert_eq!("http://user:pw@proxy.internal:3128", format!("{}", proxy));
+//!
+//! let no_proxy = "localhost,.corp.example.com,10.0.0.0/8".parse::<NoProxy>().unwrap();
+//! let uri = "https://wiki.corp.example.com/".parse::<URI<String>>().unwrap();
+//! assert!(no_proxy.bypasses(&uri));
+//! ```
+use crate::{AurisParseErrorKind, Host, ParseError, URI};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// Parse the value of `http_proxy` style variables
+///
+/// The scheme is optional and defaults to `http`, credentials are allowed.
+pub fn parse_proxy_url(value: &str) -> Result<URI<String>, ParseError> {
+    let value = value.trim();
+    if value.contains("://") {
+        value.parse()
+    } else {
+        format!("http://{}", value).parse()
+    }
+}
+
+/// Whether `ip` is within the network `net/prefix`
+pub(crate) fn cidr_contains(net: IpAddr, prefix: u8, ip: IpAddr) -> bool {
+    match (net, ip) {
+        (IpAddr::V4(net), IpAddr::V4(ip)) if prefix <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(net) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(net), IpAddr::V6(ip)) if prefix <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(net) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Parse `net/prefix` CIDR notation
+pub(crate) fn parse_cidr(s: &str) -> Result<(IpAddr, u8), ParseError> {
+    let failed = || ParseError::from(AurisParseErrorKind::Failed);
+    let (net, prefix) = s.split_once('/').ok_or_else(failed)?;
+    let net = net.parse::<IpAddr>().map_err(|_| failed())?;
+    let prefix = prefix.parse::<u8>().map_err(|_| failed())?;
+    let max = if net.is_ipv4() { 32 } else { 128 };
+    if prefix > max {
+        return Err(failed());
+    }
+    Ok((net, prefix))
+}
+
+/// A single `no_proxy` entry
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum NoProxyEntry {
+    /// `*`, nothing is proxied
+    Wildcard,
+    /// A domain and all of its subdomains
+    Domain(String),
+    Ip(IpAddr),
+    Cidr(IpAddr, u8),
+}
+
+impl FromStr for NoProxyEntry {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let failed = || ParseError::from(AurisParseErrorKind::Failed);
+        if s == "*" {
+            return Ok(NoProxyEntry::Wildcard);
+        }
+        let unbracketed = s.trim_start_matches('[').trim_end_matches(']');
+        if s.contains('/') {
+            let (net, prefix) = parse_cidr(s)?;
+            return Ok(NoProxyEntry::Cidr(net, prefix));
+        }
+        if let Ok(ip) = unbracketed.parse::<IpAddr>() {
+            return Ok(NoProxyEntry::Ip(ip));
+        }
+        let domain = s.trim_start_matches('*').trim_start_matches('.');
+        if domain.is_empty() {
+            return Err(failed());
+        }
+        Ok(NoProxyEntry::Domain(domain.to_ascii_lowercase()))
+    }
+}
+
+/// A parsed `no_proxy` list
+///
+/// Entries are separated by commas or whitespace. Domains match themselves
+/// and their subdomains, a leading `.` or `*.` is ignored.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct NoProxy {
+    pub entries: Vec<NoProxyEntry>,
+}
+
+impl FromStr for NoProxy {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let entries = s
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|e| !e.is_empty())
+            .map(|e| e.parse())
+            .collect::<Result<Vec<NoProxyEntry>, ParseError>>()?;
+        Ok(NoProxy { entries })
+    }
+}
+
+impl NoProxy {
+    /// Whether requests to `uri` should skip the proxy
+    pub fn bypasses(&self, uri: &URI<String>) -> bool {
+        let host = Host::from_authority_host(&uri.authority.host);
+        let ip = match host {
+            Some(Host::Ipv4(ip)) => Some(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => Some(IpAddr::V6(ip)),
+            _ => None,
+        };
+        let domain = uri.authority.host.to_ascii_lowercase();
+
+        self.entries.iter().any(|entry| match entry {
+            NoProxyEntry::Wildcard => true,
+            NoProxyEntry::Ip(entry) => ip == Some(*entry),
+            NoProxyEntry::Cidr(net, prefix) => {
+                ip.is_some_and(|ip| cidr_contains(*net, *prefix, ip))
+            }
+            NoProxyEntry::Domain(entry) => {
+                ip.is_none()
+                    && (domain == *entry
+                        || domain
+                            .strip_suffix(entry.as_str())
+                            .is_some_and(|rest| rest.ends_with('.')))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bypasses(no_proxy: &str, uri: &str) -> bool {
+        no_proxy
+            .parse::<NoProxy>()
+            .unwrap()
+            .bypasses(&uri.parse::<URI<String>>().unwrap())
+    }
+
+    #[test]
+    fn test_no_proxy_matching() {
+        let list = "example.com, *.internal 192.168.1.10,10.0.0.0/8,[::1],fd00::/8";
+        assert!(bypasses(list, "http://example.com/"));
+        assert!(bypasses(list, "http://API.Example.com/"));
+        assert!(!bypasses(list, "http://notexample.com/"));
+        assert!(bypasses(list, "http://db.internal/"));
+        assert!(bypasses(list, "http://192.168.1.10:8080/"));
+        assert!(!bypasses(list, "http://192.168.1.11/"));
+        assert!(bypasses(list, "http://10.20.30.40/"));
+        assert!(bypasses(list, "http://[::1]/"));
+        assert!(bypasses(list, "http://[fd12::1]/"));
+        assert!(!bypasses(list, "http://[fe80::1]/"));
+        assert!(bypasses("*", "http://anything/"));
+    }
+
+    #[test]
+    fn test_bad_no_proxy() {
+        assert!("10.0.0.0/33".parse::<NoProxy>().is_err());
+        assert!("example.com/8".parse::<NoProxy>().is_err());
+    }
+
+    #[test]
+    fn test_proxy_url() {
+        let proxy = parse_proxy_url("socks5://proxy:1080").unwrap();
+        assert_eq!(proxy.scheme, "socks5");
+        assert_eq!(proxy.authority.port, Some(1080));
+    }
+}
@@ -0,0 +1,589 @@
+//! Filtering and inspection of query strings
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::URI;
+//!
+//! let mut uri = "https://example.com/cb?code=1&token=secret&sig=abc"
+//!     .parse::<URI<String>>()
+//!     .unwrap();
+//! uri.deny_query_params(&["token", "sig"]);
+//!
+//! assert_eq!("https://example.com/cb?code=1", format!("{}", uri));
+//! ```
+use crate::charset::Charset;
+use crate::{percent, AurisParseErrorKind, DuplicateKeys, ParseError, QueryMap, QueryPairs, URI};
+use std::borrow::Cow;
+use std::collections::{btree_map, hash_map, BTreeMap, HashMap, HashSet};
+use std::hash::{BuildHasher, Hash};
+use std::iter::Map;
+use std::mem;
+use std::slice;
+
+/// How [`URI::decode_query`] decodes query keys and values
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum QueryDecoding {
+    /// `%XX` escapes
+    Percent,
+    /// `%XX` escapes and `+` as a space, as HTML forms
+    /// (`application/x-www-form-urlencoded`) encode them
+    Form,
+}
+
+/// A container the query of a URI is parsed into, the `Q` of
+/// [`URI<String, Q>`](URI)
+///
+/// Implemented for `HashMap`s with any hasher, [`QueryMap`] being the
+/// default, and `BTreeMap`s, which hold one value per key, and for
+/// [`QueryPairs`](crate::QueryPairs), which keeps every pair in order.
+///
+/// # Examples
+///
+/// ```
+/// use auris::URI;
+/// use std::collections::hash_map::{DefaultHasher, HashMap};
+/// use std::hash::BuildHasherDefault;
+///
+/// type UnseededMap = HashMap<String, String, BuildHasherDefault<DefaultHasher>>;
+/// let uri: URI<String, UnseededMap> = "http://h/?a=1".parse().unwrap();
+/// assert_eq!(uri.qs.unwrap()["a"], "1");
+/// ```
+pub trait QueryStore: Default {
+    /// The iterator returned by [`QueryStore::pairs`]
+    type Pairs<'a>: Iterator<Item = (&'a String, &'a String)>
+    where
+        Self: 'a;
+
+    /// The pairs, in the order they are stored
+    fn pairs(&self) -> Self::Pairs<'_>;
+
+    /// Add a pair, returning false if `key` is already there and the store
+    /// holds one value per key
+    fn push_pair(&mut self, key: String, value: String) -> bool;
+}
+
+impl<S: BuildHasher + Default> QueryStore for HashMap<String, String, S> {
+    type Pairs<'a>
+        = hash_map::Iter<'a, String, String>
+    where
+        S: 'a;
+
+    fn pairs(&self) -> Self::Pairs<'_> {
+        self.iter()
+    }
+
+    fn push_pair(&mut self, key: String, value: String) -> bool {
+        match self.entry(key) {
+            hash_map::Entry::Occupied(_) => false,
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(value);
+                true
+            }
+        }
+    }
+}
+
+impl QueryStore for BTreeMap<String, String> {
+    type Pairs<'a> = btree_map::Iter<'a, String, String>;
+
+    fn pairs(&self) -> Self::Pairs<'_> {
+        self.iter()
+    }
+
+    fn push_pair(&mut self, key: String, value: String) -> bool {
+        match self.entry(key) {
+            btree_map::Entry::Occupied(_) => false,
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(value);
+                true
+            }
+        }
+    }
+}
+
+impl QueryStore for Vec<(String, String)> {
+    type Pairs<'a> =
+        Map<slice::Iter<'a, (String, String)>, fn(&(String, String)) -> (&String, &String)>;
+
+    fn pairs(&self) -> Self::Pairs<'_> {
+        self.iter().map(|(k, v)| (k, v))
+    }
+
+    fn push_pair(&mut self, key: String, value: String) -> bool {
+        self.push((key, value));
+        true
+    }
+}
+
+/// Decode a query key or value as UTF-8, with `+` as a space
+pub(crate) fn decode_form(s: &str) -> Cow<'_, str> {
+    if s.contains('+') {
+        Cow::Owned(percent::decode(&s.replace('+', " ")).into_owned())
+    } else {
+        percent::decode(s)
+    }
+}
+
+impl URI<String> {
+    /// Keep only the query pairs for which `f` returns true
+    ///
+    /// The query is dropped entirely when no pairs are left.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let mut uri = "https://example.com/?a=1&b=2".parse::<URI<String>>().unwrap();
+    /// uri.retain_query(|k, _| k != "b");
+    ///
+    /// assert_eq!("https://example.com/?a=1", format!("{}", uri));
+    /// ```
+    pub fn retain_query<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &str) -> bool,
+    {
+        if let Some(qs) = self.qs.as_mut() {
+            qs.retain(|k, v| f(k, v));
+            if qs.is_empty() {
+                self.qs = None;
+            }
+        }
+    }
+
+    /// Keep only the query parameters named in `keys`
+    pub fn allow_query_params(&mut self, keys: &[&str]) {
+        self.retain_query(|k, _| keys.contains(&k))
+    }
+
+    /// Remove the query parameters named in `keys`
+    pub fn deny_query_params(&mut self, keys: &[&str]) {
+        self.retain_query(|k, _| !keys.contains(&k))
+    }
+
+    /// Rename the query parameter `old` to `new`, keeping its value
+    ///
+    /// A value `new` already had is replaced. Returns whether `old` was
+    /// there to rename.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let mut uri = "https://example.com/list?page=3".parse::<URI<String>>().unwrap();
+    /// assert!(uri.rename_query_param("page", "offset"));
+    ///
+    /// assert_eq!("https://example.com/list?offset=3", format!("{}", uri));
+    /// ```
+    pub fn rename_query_param(&mut self, old: &str, new: &str) -> bool {
+        let qs = match self.qs.as_mut() {
+            Some(qs) => qs,
+            None => return false,
+        };
+        match qs.remove(old) {
+            Some(value) => {
+                qs.insert(new.to_string(), value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace the value of every query pair with what `f` returns for it
+    ///
+    /// Keys and values are passed and returned as they appear in the URI,
+    /// percent encoded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let mut uri = "https://example.com/list?page=3".parse::<URI<String>>().unwrap();
+    /// uri.map_query_values(|k, v| match (k, v.parse::<u32>()) {
+    ///     ("page", Ok(page)) => (page * 20).to_string(),
+    ///     _ => v.to_string(),
+    /// });
+    ///
+    /// assert_eq!("https://example.com/list?page=60", format!("{}", uri));
+    /// ```
+    pub fn map_query_values<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &str) -> String,
+    {
+        for (k, v) in self.qs.iter_mut().flatten() {
+            *v = f(k, v);
+        }
+    }
+
+    /// The value of the query parameter `key`, ignoring ASCII case
+    ///
+    /// An exact match is preferred. Otherwise, if several keys only differ in
+    /// case, the value of the smallest one is returned so the result doesn't
+    /// depend on map order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "https://example.com/?PageSize=20".parse::<URI<String>>().unwrap();
+    ///
+    /// assert_eq!(uri.query_param_ignore_case("pagesize"), Some("20"));
+    /// assert_eq!(uri.query_param_ignore_case("page"), None);
+    /// ```
+    pub fn query_param_ignore_case(&self, key: &str) -> Option<&str> {
+        let qs = self.qs.as_ref()?;
+        if let Some(value) = qs.get(key) {
+            return Some(value);
+        }
+        qs.iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(key))
+            .min_by_key(|(k, _)| k.as_str())
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Iterate over the query pairs, decoding them as UTF-8 as they're reached
+    ///
+    /// `+` is decoded as a space. Keys and values without escapes are
+    /// borrowed. Pairs come in map order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let uri = "https://example.com/?q=caf%C3%A9+au+lait".parse::<URI<String>>().unwrap();
+    /// let pairs: Vec<_> = uri.query_pairs().collect();
+    ///
+    /// assert_eq!(pairs, vec![("q".into(), "café au lait".into())]);
+    /// ```
+    pub fn query_pairs(&self) -> impl Iterator<Item = (Cow<'_, str>, Cow<'_, str>)> + '_ {
+        self.qs
+            .iter()
+            .flatten()
+            .map(|(k, v)| (decode_form(k), decode_form(v)))
+    }
+
+    /// The query with keys and values decoded as UTF-8, to look pairs up by
+    /// their text
+    ///
+    /// `qs` keeps the pairs as written, so the URI displays unchanged.
+    /// Invalid UTF-8 is replaced. Keys that are only the same once decoded,
+    /// such as `a+b` and `a%20b`, keep one of their values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::{QueryDecoding, URI};
+    ///
+    /// let uri = "https://example.com/?search+term=caf%C3%A9&a%2Bb=1".parse::<URI<String>>().unwrap();
+    ///
+    /// let form = uri.decode_query(QueryDecoding::Form).unwrap();
+    /// assert_eq!(form.get("search term").map(String::as_str), Some("café"));
+    /// let percent = uri.decode_query(QueryDecoding::Percent).unwrap();
+    /// assert_eq!(percent.get("a+b").map(String::as_str), Some("1"));
+    /// assert_eq!(uri.qs.unwrap()["search+term"], "caf%C3%A9");
+    /// ```
+    pub fn decode_query(&self, decoding: QueryDecoding) -> Option<QueryMap<String, String>> {
+        let decode = |s: &str| match decoding {
+            QueryDecoding::Percent => percent::decode(s).into_owned(),
+            QueryDecoding::Form => decode_form(s).into_owned(),
+        };
+        self.qs
+            .as_ref()
+            .map(|qs| qs.iter().map(|(k, v)| (decode(k), decode(v))).collect())
+    }
+
+    /// The query with escapes in keys and values decoded using `charset`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    /// use auris::charset::Latin1;
+    ///
+    /// let uri = "http://example.com/?q=caf%E9+cr%E8me".parse::<URI<String>>().unwrap();
+    /// let decoded = uri.decode_query_with(&Latin1).unwrap();
+    ///
+    /// assert_eq!(decoded["q"], "café crème");
+    /// ```
+    pub fn decode_query_with<C>(&self, charset: &C) -> Option<QueryMap<String, String>>
+    where
+        C: Charset + ?Sized,
+    {
+        let decode = |s: &str| percent::decode_with(&s.replace('+', " "), charset);
+        self.qs
+            .as_ref()
+            .map(|qs| qs.iter().map(|(k, v)| (decode(k), decode(v))).collect())
+    }
+}
+
+impl URI<String, QueryPairs<String, String>> {
+    /// Remove repeated query keys, keeping the pairs in the order their
+    /// keys first appear
+    ///
+    /// `policy` chooses between the values of a repeated key as
+    /// [`ParseOptions::duplicate_keys`](crate::ParseOptions::duplicate_keys)
+    /// does, except that [`DuplicateKeys::CollectAll`] merges them with
+    /// commas. Empty values add nothing to a merge. With
+    /// [`DuplicateKeys::Error`] a repeated key fails with
+    /// [`AurisParseErrorKind::DuplicateQueryKey`] and the query is left as
+    /// it was.
+    ///
+    /// The pairs only repeat keys when parsed with
+    /// [`DuplicateKeys::CollectAll`], or built by hand.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::{DuplicateKeys, ParseOptions, QueryPairs, URI};
+    ///
+    /// let options = ParseOptions::default().duplicate_keys(DuplicateKeys::CollectAll);
+    /// let mut uri: URI<String, QueryPairs<String, String>> =
+    ///     options.parse("https://example.com/?tag=a&id=7&tag=b").unwrap();
+    /// uri.dedup_query(DuplicateKeys::CollectAll).unwrap();
+    ///
+    /// assert_eq!("https://example.com/?tag=a,b&id=7", format!("{}", uri));
+    /// ```
+    pub fn dedup_query(&mut self, policy: DuplicateKeys) -> Result<(), ParseError> {
+        if let Some(qs) = self.qs.as_mut() {
+            let mut seen = HashSet::new();
+            if policy == DuplicateKeys::Error && !qs.iter().all(|(k, _)| seen.insert(k)) {
+                return Err(AurisParseErrorKind::DuplicateQueryKey.into());
+            }
+            *qs = dedup_pairs(mem::take(qs), policy, merge_value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Remove repeated keys from a raw query string, keeping the pairs in the
+/// order their keys first appear
+///
+/// This cleans up the query of a URL before it is parsed or passed on,
+/// with `policy` choosing between the values as [`URI::dedup_query`] does.
+/// Keys without a value are kept without one, and empty pairs such as the
+/// one in `a&&b` are dropped. The leading `?` is optional and left out of
+/// the result.
+///
+/// # Examples
+///
+/// ```
+/// use auris::{query, DuplicateKeys};
+///
+/// let messy = "?utm_source=a&id=7&utm_source=b&id=7";
+/// assert_eq!(query::dedup_query(messy, DuplicateKeys::FirstWins).unwrap(), "utm_source=a&id=7");
+/// assert_eq!(query::dedup_query(messy, DuplicateKeys::LastWins).unwrap(), "utm_source=b&id=7");
+/// assert_eq!(
+///     query::dedup_query(messy, DuplicateKeys::CollectAll).unwrap(),
+///     "utm_source=a,b&id=7,7"
+/// );
+/// assert!(query::dedup_query(messy, DuplicateKeys::Error).is_err());
+/// ```
+pub fn dedup_query(query: &str, policy: DuplicateKeys) -> Result<String, ParseError> {
+    if let Some(offset) = query.find(|c: char| c == '#' || c.is_whitespace()) {
+        return Err(AurisParseErrorKind::DisallowedCharacter { offset }.into());
+    }
+    let query = query.strip_prefix('?').unwrap_or(query);
+    let pairs = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((k, v)) => (k, Some(v.to_string())),
+            None => (pair, None),
+        });
+    let deduped = dedup_pairs(pairs, policy, |value, new| match (value.as_mut(), new) {
+        (_, None) => (),
+        (None, new) => *value = new,
+        (Some(value), Some(new)) => merge_value(value, new),
+    })?;
+    Ok(deduped
+        .into_iter()
+        .map(|(k, v)| match v {
+            Some(v) => format!("{}={}", k, v),
+            None => k.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&"))
+}
+
+/// Keep the first of each key with the value `policy` picks, where
+/// [`DuplicateKeys::CollectAll`] combines the values with `merge`
+fn dedup_pairs<K, V, I, F>(
+    pairs: I,
+    policy: DuplicateKeys,
+    merge: F,
+) -> Result<Vec<(K, V)>, ParseError>
+where
+    K: Eq + Hash + Clone,
+    I: IntoIterator<Item = (K, V)>,
+    F: Fn(&mut V, V),
+{
+    let mut deduped: Vec<(K, V)> = Vec::new();
+    let mut positions: HashMap<K, usize> = HashMap::new();
+    for (k, v) in pairs {
+        let value = match positions.get(&k) {
+            Some(position) => &mut deduped[*position].1,
+            None => {
+                positions.insert(k.clone(), deduped.len());
+                deduped.push((k, v));
+                continue;
+            }
+        };
+        match policy {
+            DuplicateKeys::LastWins => *value = v,
+            DuplicateKeys::FirstWins => (),
+            DuplicateKeys::Error => return Err(AurisParseErrorKind::DuplicateQueryKey.into()),
+            DuplicateKeys::CollectAll => merge(value, v),
+        }
+    }
+    Ok(deduped)
+}
+
+/// Append `new` to `value` after a comma, unless either is empty
+fn merge_value(value: &mut String, new: String) {
+    if value.is_empty() {
+        *value = new;
+    } else if !new.is_empty() {
+        value.push(',');
+        value.push_str(&new);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ParseOptions;
+
+    #[test]
+    fn test_query_param_ignore_case_prefers_exact() {
+        let uri = "http://h/?pagesize=1&PAGESIZE=2&PageSize=3"
+            .parse::<URI<String>>()
+            .unwrap();
+        assert_eq!(uri.query_param_ignore_case("PageSize"), Some("3"));
+        assert_eq!(uri.query_param_ignore_case("pageSIZE"), Some("2"));
+    }
+
+    #[test]
+    fn test_query_pairs_borrow_when_possible() {
+        let uri = "http://h/?plain=x&a%26b=1+2"
+            .parse::<URI<String>>()
+            .unwrap();
+        let mut pairs: Vec<_> = uri.query_pairs().collect();
+        pairs.sort();
+
+        assert!(matches!(
+            pairs[1],
+            (Cow::Borrowed("plain"), Cow::Borrowed("x"))
+        ));
+        assert_eq!(pairs[0], ("a&b".into(), "1 2".into()));
+        assert_eq!(
+            "http://h/"
+                .parse::<URI<String>>()
+                .unwrap()
+                .query_pairs()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_rename_and_map_query() {
+        let mut uri = "http://h/?page=2&offset=9&q=a"
+            .parse::<URI<String>>()
+            .unwrap();
+        assert!(uri.rename_query_param("page", "offset"));
+        assert!(!uri.rename_query_param("page", "offset"));
+        let qs = uri.qs.as_ref().unwrap();
+        assert_eq!(qs.len(), 2);
+        assert_eq!(qs.get("offset").map(String::as_str), Some("2"));
+
+        uri.map_query_values(|k, v| format!("{}-{}", k, v));
+        assert_eq!(uri.query_param_ignore_case("q"), Some("q-a"));
+
+        let mut bare = "http://h/".parse::<URI<String>>().unwrap();
+        assert!(!bare.rename_query_param("a", "b"));
+        bare.map_query_values(|_, _| unreachable!());
+        assert_eq!(bare.qs, None);
+    }
+
+    #[test]
+    fn test_decode_query() {
+        let uri = "http://h/?a%20b=1+2%2B3&a+b=x&%FF=%zz&k=a%26b"
+            .parse::<URI<String>>()
+            .unwrap();
+        let percent = uri.decode_query(QueryDecoding::Percent).unwrap();
+        assert_eq!(percent["a b"], "1+2+3");
+        assert_eq!(percent["a+b"], "x");
+        assert_eq!(percent["\u{fffd}"], "%zz");
+        assert_eq!(percent["k"], "a&b");
+        let form = uri.decode_query(QueryDecoding::Form).unwrap();
+        assert_eq!(form.len(), 3);
+        assert!(form["a b"] == "x" || form["a b"] == "1 2+3");
+        assert_eq!(uri.to_string().matches("a%26b").count(), 1);
+        let bare = "http://h/".parse::<URI<String>>().unwrap();
+        assert_eq!(bare.decode_query(QueryDecoding::Form), None);
+    }
+
+    #[test]
+    fn test_dedup_query_keeps_first_position() {
+        let dedup = |q: &str, policy| dedup_query(q, policy).unwrap();
+        assert_eq!(dedup("b=1&a=2&b=3", DuplicateKeys::LastWins), "b=3&a=2");
+        assert_eq!(dedup("flag&flag", DuplicateKeys::CollectAll), "flag");
+        assert_eq!(dedup("a&a=1&a=&a=2", DuplicateKeys::CollectAll), "a=1,2");
+        assert_eq!(dedup("a=1&a", DuplicateKeys::LastWins), "a");
+        assert_eq!(dedup("a&&b&", DuplicateKeys::Error), "a&b");
+        assert_eq!(dedup("", DuplicateKeys::Error), "");
+        assert_eq!(dedup("?", DuplicateKeys::Error), "");
+        assert!(dedup_query("a#b", DuplicateKeys::FirstWins).is_err());
+    }
+
+    #[test]
+    fn test_uri_dedup_query() {
+        let options = ParseOptions::default().duplicate_keys(DuplicateKeys::CollectAll);
+        let parse =
+            |s: &str| -> URI<String, QueryPairs<String, String>> { options.parse(s).unwrap() };
+        let mut uri = parse("http://h/?b=1&a=&b=2&a=3&b=");
+        uri.dedup_query(DuplicateKeys::CollectAll).unwrap();
+        assert_eq!(uri.to_string(), "http://h/?b=1,2&a=3");
+
+        let mut uri = parse("http://h/?b=1&a=2&b=3");
+        uri.dedup_query(DuplicateKeys::FirstWins).unwrap();
+        assert_eq!(uri.to_string(), "http://h/?b=1&a=2");
+
+        let mut uri = parse("http://h/?b=1&a=2&b=3");
+        uri.dedup_query(DuplicateKeys::LastWins).unwrap();
+        assert_eq!(uri.to_string(), "http://h/?b=3&a=2");
+
+        let mut uri = parse("http://h/?b=1&a=2&b=3");
+        assert!(uri.dedup_query(DuplicateKeys::Error).is_err());
+        assert_eq!(uri, parse("http://h/?b=1&a=2&b=3"));
+
+        let mut bare = parse("http://h/");
+        bare.dedup_query(DuplicateKeys::Error).unwrap();
+        assert_eq!(bare.qs, None);
+    }
+
+    #[test]
+    fn test_allow_query_params() {
+        let mut uri = "https://example.com/?a=1&b=2&c=3"
+            .parse::<URI<String>>()
+            .unwrap();
+        uri.allow_query_params(&["a", "c"]);
+
+        let query_string_map = [
+            ("a".to_string(), "1".to_string()),
+            ("c".to_string(), "3".to_string()),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(uri.qs, Some(query_string_map));
+
+        uri.allow_query_params(&[]);
+        assert_eq!(uri.qs, None);
+    }
+}
@@ -0,0 +1,82 @@
+//! Telling the kinds of URI references apart (RFC 3986 section 4)
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::reference::UriReference;
+//!
+//! assert_eq!(UriReference::classify("https://example.com/"), UriReference::Absolute);
+//! assert_eq!(UriReference::classify("//cdn.example.com/app.js"), UriReference::NetworkPath);
+//! assert_eq!(UriReference::classify("../img/logo.png"), UriReference::RelativePath);
+//! assert_eq!(UriReference::classify("#top"), UriReference::SameDocument);
+//! ```
+use crate::parsers;
+
+/// The kind of a URI reference, which decides how it's resolved against a
+/// base URI
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum UriReference {
+    /// Starts with a scheme, `https://example.com/a` or `mailto:a@b`
+    Absolute,
+    /// Starts with `//`, and takes only the scheme from the base
+    NetworkPath,
+    /// Starts with a single `/`, and takes the scheme and authority from the
+    /// base
+    AbsolutePath,
+    /// A path relative to the base's path, such as `a/b`, `../c` or `?q`
+    RelativePath,
+    /// Empty, or only a fragment, so it stays within the base document
+    SameDocument,
+}
+
+impl UriReference {
+    /// Classify `input` without parsing the rest of it
+    ///
+    /// A first segment containing a `:` is a scheme if it's a valid one, so
+    /// `a:b` is absolute. Write `./a:b` for a relative path.
+    pub fn classify(input: &str) -> UriReference {
+        let first_segment = input
+            .find(['/', '?', '#'])
+            .map_or(input, |end| &input[..end]);
+        if let Some((scheme, _)) = first_segment.split_once(':') {
+            if parsers::is_valid_scheme(scheme) {
+                return UriReference::Absolute;
+            }
+        }
+        if input.starts_with("//") {
+            UriReference::NetworkPath
+        } else if input.starts_with('/') {
+            UriReference::AbsolutePath
+        } else if input.is_empty() || input.starts_with('#') {
+            UriReference::SameDocument
+        } else {
+            UriReference::RelativePath
+        }
+    }
+
+    /// Whether the reference has to be resolved against a base URI
+    pub fn is_relative(self) -> bool {
+        self != UriReference::Absolute
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            UriReference::classify("urn:isbn:123"),
+            UriReference::Absolute
+        );
+        assert_eq!(UriReference::classify("a+b.c-d:x"), UriReference::Absolute);
+        assert_eq!(UriReference::classify("1a:b"), UriReference::RelativePath);
+        assert_eq!(UriReference::classify("./a:b"), UriReference::RelativePath);
+        assert_eq!(UriReference::classify("a/b:c"), UriReference::RelativePath);
+        assert_eq!(UriReference::classify("?q=1"), UriReference::RelativePath);
+        assert_eq!(UriReference::classify("/a:b"), UriReference::AbsolutePath);
+        assert_eq!(UriReference::classify(""), UriReference::SameDocument);
+        assert!(!UriReference::classify("HTTP://h").is_relative());
+    }
+}
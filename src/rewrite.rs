@@ -0,0 +1,212 @@
+//! Rule based rewriting of URIs
+//!
+//! A [`Rewriter`](struct.Rewriter.html) holds an ordered list of rules. Each
+//! rule matches on the scheme, host and a path prefix of a URI and then
+//! changes the host, prefixes the path or adds and removes query parameters.
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::URI;
+//! use auris::rewrite::{Rewriter, Rule};
+//!
+//! let rewriter = Rewriter::new().rule(
+//!     Rule::new()
+//!         .when_host("blog.example.com")
+//!         .set_host("example.com")
+//!         .prefix_path("/blog")
+//!         .remove_param("sid"),
+//! );
+//!
+//! let mut uri = "https://blog.example.com/posts?sid=123".parse::<URI<String>>().unwrap();
+//! rewriter.rewrite(&mut uri);
+//!
+//! assert_eq!("https://example.com/blog/posts", format!("{}", uri));
+//! ```
+use crate::{QueryMap, URI};
+
+fn segments(path: &str) -> Vec<String> {
+    path.trim_start_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+enum Action {
+    SetHost(String),
+    PrefixPath(Vec<String>),
+    AddParam(String, String),
+    RemoveParam(String),
+}
+
+/// A single rewrite rule
+///
+/// The `when_*` methods narrow what the rule matches, a rule without
+/// conditions matches every URI. The remaining methods add actions, which
+/// are run in the order they were added.
+#[derive(Debug, Clone, Default)]
+pub struct Rule {
+    scheme: Option<String>,
+    host: Option<String>,
+    path_prefix: Vec<String>,
+    actions: Vec<Action>,
+}
+
+impl Rule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match URIs with this scheme (case insensitive)
+    pub fn when_scheme(mut self, scheme: &str) -> Self {
+        self.scheme = Some(scheme.to_string());
+        self
+    }
+
+    /// Only match URIs with this host (case insensitive)
+    pub fn when_host(mut self, host: &str) -> Self {
+        self.host = Some(host.to_string());
+        self
+    }
+
+    /// Only match URIs whose path starts with the segments of `prefix`
+    pub fn when_path_prefix(mut self, prefix: &str) -> Self {
+        self.path_prefix = segments(prefix);
+        self
+    }
+
+    /// Replace the host
+    pub fn set_host(mut self, host: &str) -> Self {
+        self.actions.push(Action::SetHost(host.to_string()));
+        self
+    }
+
+    /// Put the segments of `prefix` in front of the path
+    pub fn prefix_path(mut self, prefix: &str) -> Self {
+        self.actions.push(Action::PrefixPath(segments(prefix)));
+        self
+    }
+
+    /// Add a query parameter, replacing any existing value
+    pub fn add_param(mut self, key: &str, value: &str) -> Self {
+        self.actions
+            .push(Action::AddParam(key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Remove a query parameter
+    pub fn remove_param(mut self, key: &str) -> Self {
+        self.actions.push(Action::RemoveParam(key.to_string()));
+        self
+    }
+
+    /// Whether this rule applies to `uri`
+    pub fn matches(&self, uri: &URI<String>) -> bool {
+        let scheme_matches = self
+            .scheme
+            .as_ref()
+            .is_none_or(|s| s.eq_ignore_ascii_case(&uri.scheme));
+        let host_matches = self
+            .host
+            .as_ref()
+            .is_none_or(|h| h.eq_ignore_ascii_case(&uri.authority.host));
+        let path = uri.path.as_deref().unwrap_or(&[]);
+        let path_matches = path.starts_with(&self.path_prefix);
+        scheme_matches && host_matches && path_matches
+    }
+
+    fn apply(&self, uri: &mut URI<String>) {
+        for action in self.actions.iter() {
+            match action {
+                Action::SetHost(host) => uri.authority.host = host.clone(),
+                Action::PrefixPath(prefix) => {
+                    let path = uri.path.get_or_insert_with(Vec::new);
+                    path.splice(0..0, prefix.iter().cloned());
+                }
+                Action::AddParam(key, value) => {
+                    uri.qs
+                        .get_or_insert_with(QueryMap::default)
+                        .insert(key.clone(), value.clone());
+                }
+                Action::RemoveParam(key) => uri.deny_query_params(&[key]),
+            }
+        }
+    }
+}
+
+/// An ordered list of rewrite rules
+#[derive(Debug, Clone, Default)]
+pub struct Rewriter {
+    rules: Vec<Rule>,
+}
+
+impl Rewriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a rule, rules run in the order they were added
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Apply every matching rule to `uri` in order
+    ///
+    /// Each rule is matched against the result of the rules before it.
+    /// Returns the number of rules that were applied.
+    pub fn rewrite(&self, uri: &mut URI<String>) -> usize {
+        let mut applied = 0;
+        for rule in self.rules.iter() {
+            if rule.matches(uri) {
+                rule.apply(uri);
+                applied += 1;
+            }
+        }
+        applied
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rules_run_in_order() {
+        let rewriter = Rewriter::new()
+            .rule(
+                Rule::new()
+                    .when_scheme("http")
+                    .when_path_prefix("/api")
+                    .set_host("internal")
+                    .add_param("via", "proxy"),
+            )
+            .rule(Rule::new().when_host("internal").prefix_path("/v1"))
+            .rule(Rule::new().when_host("example.com").set_host("nope"));
+
+        let mut uri = "http://example.com/api/users"
+            .parse::<URI<String>>()
+            .unwrap();
+        assert_eq!(rewriter.rewrite(&mut uri), 2);
+        assert_eq!(uri.authority.host, "internal");
+        assert_eq!(
+            uri.path,
+            Some(vec![
+                "v1".to_string(),
+                "api".to_string(),
+                "users".to_string()
+            ])
+        );
+        assert_eq!(uri.qs.unwrap()["via"], "proxy");
+
+        let mut untouched = "https://example.com/apis".parse::<URI<String>>().unwrap();
+        assert_eq!(
+            Rewriter::new()
+                .rule(Rule::new().when_path_prefix("/api").set_host("x"))
+                .rewrite(&mut untouched),
+            0
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Matching URIs against robots.txt `Allow` and `Disallow` rules
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::robots::RobotsRules;
+//! use auris::{QueryPairs, URI};
+//!
+//! let rules = RobotsRules::default()
+//!     .disallow("/private/")
+//!     .allow("/private/press-kit$")
+//!     .disallow("/*.pdf$");
+//!
+//! let allowed = |s: &str| {
+//!     let uri: URI<String, QueryPairs<String, String>> = s.parse().unwrap();
+//!     rules.is_allowed(&uri)
+//! };
+//! assert!(allowed("https://example.com/about"));
+//! assert!(!allowed("https://example.com/private/salaries"));
+//! assert!(allowed("https://example.com/private/press-kit"));
+//! assert!(!allowed("https://example.com/docs/guide.pdf"));
+//! ```
+use crate::{percent, PathKind, QueryPairs, URI};
+
+/// A robots.txt path pattern, as described in RFC 9309 section 2.2.2
+///
+/// The pattern matches paths starting with it. `*` matches any run of
+/// characters, and a `$` at the end anchors the pattern to the end of the
+/// path. Escapes are compared regardless of hex case, and non-ASCII
+/// characters in the pattern match their UTF-8 escapes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PathPattern {
+    pattern: String,
+    anchored: bool,
+}
+
+/// Percent encode non-ASCII and normalize escapes, so equal paths compare
+/// equal
+fn normalize(path: &str) -> String {
+    percent::normalize_escapes(&percent::encode_non_ascii(path)).into_owned()
+}
+
+impl PathPattern {
+    pub fn new(pattern: &str) -> Self {
+        let (pattern, anchored) = match pattern.strip_suffix('$') {
+            Some(pattern) => (pattern, true),
+            None => (pattern, false),
+        };
+        PathPattern {
+            pattern: normalize(pattern),
+            anchored,
+        }
+    }
+
+    /// The pattern's length, longer patterns are more specific
+    pub fn len(&self) -> usize {
+        self.pattern.len() + usize::from(self.anchored)
+    }
+
+    /// Whether the pattern is empty, as in `Disallow:`, which matches
+    /// nothing
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether `path`, the encoded path with any `?query`, matches
+    pub fn matches_path(&self, path: &str) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let path = normalize(path);
+        let (pattern, path) = (self.pattern.as_bytes(), path.as_bytes());
+        // the last `*` seen, and where in the path it started matching
+        let mut star: Option<(usize, usize)> = None;
+        let (mut p, mut i) = (0, 0);
+        loop {
+            if p == pattern.len() && (!self.anchored || i == path.len()) {
+                return true;
+            }
+            if p < pattern.len() && pattern[p] == b'*' {
+                star = Some((p, i));
+                p += 1;
+            } else if p < pattern.len() && i < path.len() && pattern[p] == path[i] {
+                p += 1;
+                i += 1;
+            } else if let Some((star_p, star_i)) = star.filter(|(_, i)| *i < path.len()) {
+                // let the `*` take one more character
+                star = Some((star_p, star_i + 1));
+                p = star_p + 1;
+                i = star_i + 1;
+            } else {
+                return false;
+            }
+        }
+    }
+
+    /// Whether the path and query of `uri` match
+    ///
+    /// The query pairs are matched in the order they were written, so
+    /// patterns spanning several pairs match as they would the raw URI.
+    pub fn matches(&self, uri: &URI<String, QueryPairs<String, String>>) -> bool {
+        self.matches_path(&robots_path(uri))
+    }
+}
+
+/// The path and query of `uri`, with an empty path as `/`
+fn robots_path(uri: &URI<String, QueryPairs<String, String>>) -> String {
+    let mut path = String::new();
+    for (i, segment) in uri.path.iter().flatten().enumerate() {
+        if i > 0 || uri.path_kind != PathKind::Rootless {
+            path.push('/');
+        }
+        path.push_str(segment);
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
+    if let Some(qs) = uri.qs.as_ref() {
+        path.push('?');
+        for (i, (k, v)) in qs.iter().enumerate() {
+            if i > 0 {
+                path.push('&');
+            }
+            path.push_str(k);
+            path.push('=');
+            path.push_str(v);
+        }
+    }
+    path
+}
+
+/// The `Allow` and `Disallow` rules of one robots.txt group
+///
+/// The longest matching pattern decides, and `Allow` wins a tie. URIs no
+/// rule matches are allowed.
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    rules: Vec<(PathPattern, bool)>,
+}
+
+impl RobotsRules {
+    /// Add an `Allow` rule
+    pub fn allow(mut self, pattern: &str) -> Self {
+        self.rules.push((PathPattern::new(pattern), true));
+        self
+    }
+
+    /// Add a `Disallow` rule
+    pub fn disallow(mut self, pattern: &str) -> Self {
+        self.rules.push((PathPattern::new(pattern), false));
+        self
+    }
+
+    /// Whether a crawler may fetch `path`, the encoded path with any
+    /// `?query`
+    pub fn is_path_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(pattern, _)| pattern.matches_path(path))
+            .max_by_key(|(pattern, allow)| (pattern.len(), *allow))
+            .is_none_or(|(_, allow)| *allow)
+    }
+
+    /// Whether a crawler may fetch `uri`, with its query pairs in the order
+    /// they were written
+    pub fn is_allowed(&self, uri: &URI<String, QueryPairs<String, String>>) -> bool {
+        self.is_path_allowed(&robots_path(uri))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        PathPattern::new(pattern).matches_path(path)
+    }
+
+    #[test]
+    fn test_pattern_matching() {
+        assert!(matches("/", "/anything"));
+        assert!(matches("/fish", "/fish.html"));
+        assert!(!matches("/fish", "/Fish"));
+        assert!(matches("/*.php", "/folder/filename.php?parameters"));
+        assert!(matches("/*.php$", "/filename.php"));
+        assert!(!matches("/*.php$", "/filename.php5"));
+        assert!(matches("/fish*.php", "/fish/salmon.php"));
+        assert!(matches("/a*b*c$", "/aXbYbZc"));
+        assert!(!matches("/a*b*c$", "/aXbYcZ"));
+        assert!(matches("/$", "/"));
+        assert!(!matches("/$", "/a"));
+        assert!(!matches("", "/"));
+    }
+
+    #[test]
+    fn test_escapes_and_unicode() {
+        assert!(matches("/%7euser", "/~user/"));
+        assert!(matches("/a%2fb", "/a%2Fb"));
+        assert!(matches("/ä", "/%C3%A4"));
+        assert!(!matches("/a%2Fb", "/a/b"));
+    }
+
+    #[test]
+    fn test_longest_match_wins() {
+        let rules = RobotsRules::default()
+            .allow("/p")
+            .disallow("/")
+            .disallow("/page")
+            .allow("/page")
+            .disallow("");
+        assert!(rules.is_path_allowed("/page"));
+        assert!(rules.is_path_allowed("/p"));
+        assert!(!rules.is_path_allowed("/x"));
+        assert!(RobotsRules::default().disallow("").is_path_allowed("/x"));
+
+        let uri = "http://example.com?q=1".parse().unwrap();
+        assert!(!RobotsRules::default().disallow("/?q=").is_allowed(&uri));
+    }
+
+    #[test]
+    fn test_query_in_written_order() {
+        let rules = RobotsRules::default().disallow("/*?a=1&b=2");
+        let uri = |s: &str| -> URI<String, QueryPairs<String, String>> { s.parse().unwrap() };
+        assert!(!rules.is_allowed(&uri("http://h/x?a=1&b=2&c=3")));
+        assert!(rules.is_allowed(&uri("http://h/x?b=2&a=1")));
+    }
+}
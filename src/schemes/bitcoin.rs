@@ -0,0 +1,194 @@
+//! Bitcoin payment URIs ([BIP 21](https://github.com/bitcoin/bips/blob/master/bip-0021.mediawiki))
+//!
+//! ```notrust
+//!     bitcoin:175tWpb8K1S7NmH4Zx6rewF9WQrcZv245W?amount=20.3&label=Luke-Jr
+//!     \_____/ \________________________________/ \_______________________/
+//!        |                    |                              |
+//!     scheme               address                       parameters
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::schemes::bitcoin::BitcoinUri;
+//!
+//! let uri = "bitcoin:175tWpb8K1S7NmH4Zx6rewF9WQrcZv245W?amount=20.3&label=Luke-Jr"
+//!     .parse::<BitcoinUri<String>>()
+//!     .unwrap();
+//!
+//! assert_eq!(uri.address, "175tWpb8K1S7NmH4Zx6rewF9WQrcZv245W");
+//! assert_eq!(uri.amount, Some(2_030_000_000));
+//! assert_eq!(uri.label, Some("Luke-Jr".to_string()));
+//! ```
+use nom::{
+    bytes::complete::take_while1,
+    combinator::{all_consuming, opt, verify},
+};
+
+use crate::parsers::{self, IResult};
+use crate::{percent, ParseError, QueryMap};
+use core::hash::Hash;
+use std::str::FromStr;
+
+/// Parameters with a meaning of their own, everything else ends up in
+/// [`BitcoinUri::params`]
+const KNOWN_PARAMS: &[&str] = &["amount", "label", "message"];
+
+/// The parts of a `bitcoin:` URI
+///
+/// Parameters starting with `req-` must be understood by the wallet, as no
+/// `req-` parameters are known a URI using one is rejected.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BitcoinUri<T>
+where
+    T: Ord + Hash,
+{
+    pub address: T,
+    /// The amount in satoshis
+    pub amount: Option<u64>,
+    /// Percent decoded when parsed with `FromStr`
+    pub label: Option<T>,
+    /// Percent decoded when parsed with `FromStr`
+    pub message: Option<T>,
+    /// Other optional parameters
+    pub params: QueryMap<T, T>,
+}
+
+impl BitcoinUri<&str> {
+    fn to_owned(&self) -> BitcoinUri<String> {
+        BitcoinUri {
+            address: self.address.to_string(),
+            amount: self.amount,
+            label: self.label.map(|l| percent::decode(l).into_owned()),
+            message: self.message.map(|m| percent::decode(m).into_owned()),
+            params: self
+                .params
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for BitcoinUri<String> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match all_consuming(bitcoin_uri)(s) {
+            Ok((_, obj)) => Ok(obj.to_owned()),
+            Err(e) => Err(ParseError::from_nom(e)),
+        }
+    }
+}
+
+/// Parse a decimal amount of bitcoin into satoshis
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes::bitcoin;
+/// assert_eq!(bitcoin::parse_amount("0.00000001"), Some(1));
+/// assert_eq!(bitcoin::parse_amount("1.5"), Some(150_000_000));
+/// assert_eq!(bitcoin::parse_amount("1e3"), None);
+/// ```
+pub fn parse_amount(amount: &str) -> Option<u64> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() || !is_digits(whole) || !is_digits(fraction) || fraction.len() > 8 {
+        return None;
+    }
+    let fraction = format!("{:0<8}", fraction).parse::<u64>().ok()?;
+    whole
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(100_000_000)?
+        .checked_add(fraction)
+}
+
+/// Whether the parameters are valid, known `req-` parameters and amounts
+fn valid_params(qs: &QueryMap<&str, &str>) -> bool {
+    qs.keys().all(|k| !k.starts_with("req-"))
+        && qs.get("amount").is_none_or(|a| parse_amount(a).is_some())
+}
+
+/// Parses a `bitcoin:` URI
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes::bitcoin;
+/// bitcoin::bitcoin_uri("bitcoin:175tWpb8K1S7NmH4Zx6rewF9WQrcZv245W?message=Donation");
+/// ```
+pub fn bitcoin_uri(input: &str) -> IResult<&str, BitcoinUri<&str>> {
+    let (i, _) = verify(parsers::bare_scheme, |s: &str| {
+        s.eq_ignore_ascii_case("bitcoin")
+    })(input)?;
+    // legacy base58 and bech32 addresses are both alphanumeric
+    let (i, address) = take_while1(|c: char| c.is_ascii_alphanumeric())(i)?;
+    let (i, qs) = opt(verify(parsers::query, valid_params))(i)?;
+    let mut params = qs.unwrap_or_default();
+
+    Ok((
+        i,
+        BitcoinUri {
+            address,
+            amount: params.get("amount").and_then(|a| parse_amount(a)),
+            label: params.get("label").copied(),
+            message: params.get("message").copied(),
+            params: {
+                params.retain(|k, _| !KNOWN_PARAMS.contains(k));
+                params
+            },
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bitcoin_uri() {
+        let params = [("somethingyoudontunderstand", "50")]
+            .iter()
+            .cloned()
+            .collect();
+
+        assert_eq!(
+            bitcoin_uri("bitcoin:175tWpb8K1S7NmH4Zx6rewF9WQrcZv245W?amount=50&somethingyoudontunderstand=50"),
+            Ok((
+                "",
+                BitcoinUri {
+                    address: "175tWpb8K1S7NmH4Zx6rewF9WQrcZv245W",
+                    amount: Some(5_000_000_000),
+                    label: None,
+                    message: None,
+                    params,
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn test_label_and_message_are_decoded() {
+        let uri = "bitcoin:175tWpb8K1S7NmH4Zx6rewF9WQrcZv245W?amount=50&label=Luke-Jr&message=Donation%20for%20project%20xyz"
+            .parse::<BitcoinUri<String>>()
+            .unwrap();
+        assert_eq!(uri.label.as_deref(), Some("Luke-Jr"));
+        assert_eq!(uri.message.as_deref(), Some("Donation for project xyz"));
+    }
+
+    #[test]
+    fn test_bad_bitcoin_uri() {
+        let bad = |s: &str| s.parse::<BitcoinUri<String>>().is_err();
+        assert!(bad(
+            "bitcoin:175tWpb8K1S7NmH4Zx6rewF9WQrcZv245W?req-somethingyoudontunderstand=50"
+        ));
+        assert!(bad(
+            "bitcoin:175tWpb8K1S7NmH4Zx6rewF9WQrcZv245W?amount=0.123456789"
+        ));
+        assert!(bad("bitcoin:175tWpb8K1S7NmH4Zx6rewF9WQrcZv245W?amount=.5"));
+        assert!(bad("bitcoin:"));
+        assert!(bad("litecoin:175tWpb8K1S7NmH4Zx6rewF9WQrcZv245W"));
+    }
+}
@@ -0,0 +1,132 @@
+//! Decentralized Identifiers ([DID Core](https://www.w3.org/TR/did-core/))
+//!
+//! ```notrust
+//!     did:example:123456789abcdefghi/path/to?service=agent#keys-1
+//!     \_/ \_____/ \________________/\_____/ \___________/ \____/
+//!      |     |            |            |          |          |
+//!   scheme method        id          path       query    fragment
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::schemes::did::Did;
+//!
+//! let did = "did:web:example.com:user:alice#key-1".parse::<Did<String>>().unwrap();
+//!
+//! assert_eq!(did.method, "web");
+//! assert_eq!(did.id, "example.com:user:alice");
+//! assert_eq!(did.fragment, Some("key-1".to_string()));
+//! ```
+use nom::{
+    bytes::complete::{tag, take_till1, take_while1},
+    combinator::{all_consuming, opt, verify},
+};
+
+use crate::parsers::{self, IResult};
+use crate::{ParseError, QueryMap};
+use core::hash::Hash;
+use std::str::FromStr;
+
+/// The parts of a `did:` URI
+///
+/// The method specific id may itself contain colons, it is kept whole.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Did<T>
+where
+    T: Ord + Hash,
+{
+    pub method: T,
+    pub id: T,
+    pub path: Vec<T>,
+    pub qs: Option<QueryMap<T, T>>,
+    pub fragment: Option<T>,
+}
+
+impl Did<&str> {
+    fn to_owned(&self) -> Did<String> {
+        Did {
+            method: self.method.to_string(),
+            id: self.id.to_string(),
+            path: self.path.iter().map(|p| (*p).to_string()).collect(),
+            qs: self.qs.as_ref().map(|qs| {
+                qs.iter()
+                    .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                    .collect()
+            }),
+            fragment: self.fragment.map(|f| f.to_string()),
+        }
+    }
+}
+
+impl FromStr for Did<String> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match all_consuming(did)(s) {
+            Ok((_, obj)) => Ok(obj.to_owned()),
+            Err(e) => Err(ParseError::from_nom(e)),
+        }
+    }
+}
+
+/// Parses a DID or DID URL
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes::did;
+/// did::did("did:example:123456789abcdefghi/path?versionId=1#keys-1");
+/// ```
+pub fn did(input: &str) -> IResult<&str, Did<&str>> {
+    let (i, _) = verify(parsers::bare_scheme, |s: &str| s == "did")(input)?;
+    // method names are lowercase letters and digits
+    let (i, method) = take_while1(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())(i)?;
+    let (i, _) = tag(":")(i)?;
+    let (i, id) = take_till1(|c: char| c == '/' || c == '?' || c == '#' || c.is_whitespace())(i)?;
+    let (i, path) = parsers::path(i)?;
+    let (i, qs) = opt(parsers::query)(i)?;
+    let (i, fragment) = opt(parsers::fragment)(i)?;
+
+    Ok((
+        i,
+        Did {
+            method,
+            id,
+            path,
+            qs,
+            fragment,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_did_url() {
+        let query_string_map = [("service", "agent")].iter().cloned().collect();
+
+        assert_eq!(
+            did("did:example:123456789abcdefghi/path/to?service=agent#keys-1"),
+            Ok((
+                "",
+                Did {
+                    method: "example",
+                    id: "123456789abcdefghi",
+                    path: vec!["path", "to"],
+                    qs: Some(query_string_map),
+                    fragment: Some("keys-1"),
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn test_bad_did() {
+        assert!("did:Example:123".parse::<Did<String>>().is_err());
+        assert!("did:example:".parse::<Did<String>>().is_err());
+        assert!("urn:example:123".parse::<Did<String>>().is_err());
+    }
+}
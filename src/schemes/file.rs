@@ -0,0 +1,379 @@
+//! `file:` URIs ([RFC 8089](https://tools.ietf.org/html/rfc8089))
+//!
+//! ```notrust
+//!     file://server/share/report.txt      file:///etc/hosts
+//!            \____/\_______________/             \________/
+//!              |           |                          |
+//!            host         path                       path
+//! ```
+//!
+//! A `file:` URI with no host, or with `localhost`, names a file on the
+//! local machine. Any other host names a file shared by that machine, which
+//! Windows writes as the UNC path `\\server\share\report.txt`. Local
+//! Windows paths keep their drive letter as the first segment, as in
+//! `file:///C:/Users/jane`.
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::schemes::file::FileUrl;
+//!
+//! let url = "file://server/share/q3%20report.txt".parse::<FileUrl<String>>().unwrap();
+//! assert_eq!(url.to_unc(), Some(r"\\server\share\q3 report.txt".to_string()));
+//!
+//! let url = FileUrl::from_unc(r"\\server\share\q3 report.txt").unwrap();
+//! assert_eq!("file://server/share/q3%20report.txt", format!("{}", url));
+//!
+//! let url = "file://localhost/etc/hosts".parse::<FileUrl<String>>().unwrap();
+//! assert!(url.is_local());
+//! assert_eq!("file:///etc/hosts", format!("{}", url));
+//! ```
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till},
+    combinator::{all_consuming, recognize, verify},
+    sequence::preceded,
+};
+
+use crate::parsers::{self, IResult};
+use crate::percent::{self, EncodeSet};
+use crate::{AurisParseErrorKind, Host, ParseError};
+use core::hash::Hash;
+use std::fmt;
+use std::str::FromStr;
+
+/// The host and path of a `file:` URI
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FileUrl<T>
+where
+    T: Ord + Hash,
+{
+    /// `None` for local files, including when the host is `localhost`
+    pub host: Option<T>,
+    /// The percent encoded path, empty or starting with `/`
+    pub path: T,
+}
+
+impl FileUrl<&str> {
+    fn to_owned(&self) -> FileUrl<String> {
+        FileUrl {
+            host: self.host.map(|h| h.to_string()),
+            path: self.path.to_string(),
+        }
+    }
+}
+
+impl FromStr for FileUrl<String> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match all_consuming(file_url)(s) {
+            Ok((_, obj)) => Ok(obj.to_owned()),
+            Err(e) => Err(ParseError::from_nom(e)),
+        }
+    }
+}
+
+impl<T> fmt::Display for FileUrl<T>
+where
+    T: Ord + Hash + fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("file://")?;
+        if let Some(host) = self.host.as_ref() {
+            write!(f, "{}", host)?;
+        }
+        write!(f, "{}", self.path)
+    }
+}
+
+impl FileUrl<String> {
+    /// Whether the file is on the local machine
+    pub fn is_local(&self) -> bool {
+        self.host.is_none()
+    }
+
+    /// The UNC path of a file on another machine, `None` for local files
+    ///
+    /// Each segment is percent decoded, invalid UTF-8 is replaced. A
+    /// segment that decodes to a `\`, `/` or NUL has no UNC path.
+    pub fn to_unc(&self) -> Option<String> {
+        let host = self.host.as_ref()?;
+        let path = windows_segments(&self.path)?;
+        Some(format!("\\\\{}{}", host, path))
+    }
+
+    /// The URI of a UNC path such as `\\server\share\file.txt`
+    ///
+    /// Verbatim (`\\?\`) and device (`\\.\`) paths are rejected, see
+    /// [`FileUrl::from_windows_path`] for verbatim paths.
+    pub fn from_unc(unc: &str) -> Result<FileUrl<String>, ParseError> {
+        let rest = unc
+            .strip_prefix("\\\\")
+            .ok_or_else(|| rejected("UNC paths start with \\\\"))?;
+        if rest.starts_with("?\\") || rest.starts_with(".\\") {
+            return Err(rejected("verbatim and device paths are not supported"));
+        }
+        Self::from_unc_parts(rest)
+    }
+
+    /// The URI of `server\share\file.txt`, the part of a UNC path after
+    /// the leading `\\`
+    fn from_unc_parts(rest: &str) -> Result<FileUrl<String>, ParseError> {
+        let (host, rest) = rest.split_once('\\').unwrap_or((rest, ""));
+        if Host::from_authority_host(host).is_none() {
+            return Err(rejected("invalid UNC server name"));
+        }
+        let path = if rest.is_empty() {
+            String::new()
+        } else {
+            encode_segments(rest.split('\\'))
+        };
+        Ok(FileUrl {
+            host: Some(host.to_string()),
+            path,
+        })
+    }
+
+    /// The Windows path of the file, as a drive path for local files such
+    /// as `C:\Users\jane` and a UNC path otherwise
+    ///
+    /// The legacy `|` drive separator of `file:///C|/Users/jane` is read as
+    /// `:`. Local files without a drive letter have no Windows path. Each
+    /// segment is percent decoded, invalid UTF-8 is replaced, and a segment
+    /// that decodes to a `\`, `/` or NUL has no Windows path, so that
+    /// `..%5C..` can't climb out of its directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::schemes::file::FileUrl;
+    ///
+    /// let url = "file:///C|/Users/jane/my%20notes.txt".parse::<FileUrl<String>>().unwrap();
+    /// assert_eq!(url.to_windows_path().unwrap(), r"C:\Users\jane\my notes.txt");
+    ///
+    /// let url = FileUrl::from_windows_path(r"\\?\D:\very\long\path").unwrap();
+    /// assert_eq!("file:///D:/very/long/path", format!("{}", url));
+    /// ```
+    pub fn to_windows_path(&self) -> Option<String> {
+        if self.host.is_some() {
+            return self.to_unc();
+        }
+        let path = windows_segments(&self.path)?;
+        let bytes = path.as_bytes();
+        let is_drive = bytes.len() >= 3
+            && bytes[0] == b'\\'
+            && bytes[1].is_ascii_alphabetic()
+            && (bytes[2] == b':' || bytes[2] == b'|')
+            && (bytes.len() == 3 || bytes[3] == b'\\');
+        if !is_drive {
+            return None;
+        }
+        let rest = match &path[3..] {
+            "" => "\\",
+            rest => rest,
+        };
+        Some(format!("{}:{}", &path[1..2], rest))
+    }
+
+    /// The URI of a Windows drive, UNC or verbatim path
+    ///
+    /// Drive paths such as `C:\Users\jane` may use `/` as well as `\`.
+    /// Verbatim paths, `\\?\C:\…` and `\\?\UNC\server\share\…`, lose their
+    /// prefix. Device paths (`\\.\`), other verbatim paths and relative
+    /// paths are rejected.
+    pub fn from_windows_path(path: &str) -> Result<FileUrl<String>, ParseError> {
+        if let Some(verbatim) = path.strip_prefix("\\\\?\\") {
+            if let Some(unc) = verbatim.strip_prefix("UNC\\") {
+                return Self::from_unc_parts(unc);
+            }
+            return Self::from_drive_path(verbatim, &['\\'])
+                .ok_or_else(|| rejected("unsupported verbatim path"));
+        }
+        if path.starts_with("\\\\") {
+            return Self::from_unc(path);
+        }
+        Self::from_drive_path(path, &['\\', '/'])
+            .ok_or_else(|| rejected("not an absolute Windows path"))
+    }
+
+    /// The URI of a `C:\…` path, with components split on any of
+    /// `separators`
+    fn from_drive_path(path: &str, separators: &[char]) -> Option<FileUrl<String>> {
+        let bytes = path.as_bytes();
+        let is_drive = bytes.len() >= 3
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':'
+            && separators.contains(&char::from(bytes[2]));
+        if !is_drive {
+            return None;
+        }
+        let segments = path[3..].split(separators);
+        Some(FileUrl {
+            host: None,
+            path: format!("/{}{}", &path[..2], encode_segments(segments)),
+        })
+    }
+}
+
+fn rejected(reason: &'static str) -> ParseError {
+    ParseError::from(AurisParseErrorKind::Rejected(reason))
+}
+
+/// Percent decode each segment of `path` and join them, each after a `\`,
+/// or `None` if a segment decodes to a separator or NUL
+fn windows_segments(path: &str) -> Option<String> {
+    let mut decoded = String::new();
+    for segment in path.split('/').skip(1) {
+        let segment = percent::decode(segment);
+        if segment.contains(&['\\', '/', '\0'][..]) {
+            return None;
+        }
+        decoded.push('\\');
+        decoded.push_str(&segment);
+    }
+    Some(decoded)
+}
+
+/// Percent encode each segment and join them, each after a `/`
+fn encode_segments<'a, I: Iterator<Item = &'a str>>(segments: I) -> String {
+    let mut path = String::new();
+    for segment in segments {
+        path.push('/');
+        path.push_str(&percent::encode(segment, &EncodeSet::PATH_SEGMENT));
+    }
+    path
+}
+
+/// Everything up to a query, fragment or whitespace
+fn rest_of_path(input: &str) -> IResult<&str, &str> {
+    take_till(|c: char| c == '?' || c == '#' || c.is_whitespace())(input)
+}
+
+/// `file:/path`, without an authority
+fn file_without_authority(input: &str) -> IResult<&str, FileUrl<&str>> {
+    let (i, _) = verify(parsers::bare_scheme, |s: &str| {
+        s.eq_ignore_ascii_case("file")
+    })(input)?;
+    let (i, path) = verify(recognize(preceded(tag("/"), rest_of_path)), |p: &str| {
+        !p.starts_with("//")
+    })(i)?;
+    Ok((i, FileUrl { host: None, path }))
+}
+
+/// `file://host/path`, where `file:////host/path` is the host written in
+/// the path, as RFC 8089 appendix E.3.2 allows
+fn file_with_authority(input: &str) -> IResult<&str, FileUrl<&str>> {
+    let (i, _) = verify(parsers::scheme, |s: &str| s.eq_ignore_ascii_case("file"))(input)?;
+    let (i, host) = parsers::host(i)?;
+    let (i, path) = verify(rest_of_path, |p: &str| p.is_empty() || p.starts_with('/'))(i)?;
+    let (host, path) = match path.strip_prefix("//") {
+        Some(unc) if host.is_empty() => {
+            let unc = unc.trim_start_matches('/');
+            unc.find('/').map_or((unc, ""), |n| unc.split_at(n))
+        }
+        _ => (host, path),
+    };
+    let host = Some(host).filter(|h| !h.is_empty() && !h.eq_ignore_ascii_case("localhost"));
+    Ok((i, FileUrl { host, path }))
+}
+
+/// Parses a `file:` URI
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes::file;
+/// file::file_url("file:///etc/hosts");
+/// ```
+pub fn file_url(input: &str) -> IResult<&str, FileUrl<&str>> {
+    alt((file_with_authority, file_without_authority))(input)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s: &str) -> FileUrl<String> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_local_forms() {
+        let local = FileUrl {
+            host: None,
+            path: "/etc/hosts".to_string(),
+        };
+        assert_eq!(parse("file:/etc/hosts"), local);
+        assert_eq!(parse("file:///etc/hosts"), local);
+        assert_eq!(parse("FILE://LocalHost/etc/hosts"), local);
+        assert_eq!(local.to_unc(), None);
+    }
+
+    #[test]
+    fn test_unc_forms() {
+        let shared = FileUrl {
+            host: Some("server".to_string()),
+            path: "/share/a.txt".to_string(),
+        };
+        assert_eq!(parse("file://server/share/a.txt"), shared);
+        assert_eq!(parse("file:////server/share/a.txt"), shared);
+        assert_eq!(parse("file://///server/share/a.txt"), shared);
+        assert_eq!(shared.to_unc().unwrap(), r"\\server\share\a.txt");
+        assert_eq!(FileUrl::from_unc(r"\\server\share\a.txt").unwrap(), shared);
+        assert_eq!(parse("file://server").to_unc().unwrap(), r"\\server");
+    }
+
+    #[test]
+    fn test_rejected() {
+        assert!("file://host:80/x".parse::<FileUrl<String>>().is_err());
+        assert!("file:relative".parse::<FileUrl<String>>().is_err());
+        assert!("file:///x?q".parse::<FileUrl<String>>().is_err());
+        assert!("http://host/x".parse::<FileUrl<String>>().is_err());
+        assert!(FileUrl::from_unc(r"\\?\C:\x").is_err());
+        assert!(FileUrl::from_unc(r"\\.\pipe\x").is_err());
+        assert!(FileUrl::from_unc(r"C:\x").is_err());
+        assert!(FileUrl::from_unc(r"\\\share").is_err());
+    }
+
+    #[test]
+    fn test_windows_paths() {
+        let drive = |path: &str| FileUrl {
+            host: None,
+            path: path.to_string(),
+        };
+        let url = FileUrl::from_windows_path(r"C:\Users\x\a b.txt").unwrap();
+        assert_eq!(url, drive("/C:/Users/x/a%20b.txt"));
+        assert_eq!(url.to_windows_path().unwrap(), r"C:\Users\x\a b.txt");
+        assert_eq!(
+            parse("file:///C:/Users/x").to_windows_path().unwrap(),
+            r"C:\Users\x"
+        );
+        assert_eq!(parse("file:///c|").to_windows_path().unwrap(), r"c:\");
+        assert_eq!(FileUrl::from_windows_path("C:/a").unwrap(), drive("/C:/a"));
+        assert_eq!(FileUrl::from_windows_path(r"C:\").unwrap(), drive("/C:/"));
+        assert_eq!(drive("/C:/").to_windows_path().unwrap(), r"C:\");
+        assert_eq!(drive("/etc/hosts").to_windows_path(), None);
+        assert_eq!(drive("/CD/x").to_windows_path(), None);
+        assert_eq!(drive("/C:/a/..%5C..%5Cb").to_windows_path(), None);
+        assert_eq!(drive("/C:/a%00b").to_windows_path(), None);
+        assert_eq!(parse("file://server/share/..%5C..%5Cx").to_unc(), None);
+        assert_eq!(parse("file://server/share/..%2Fx").to_windows_path(), None);
+
+        let verbatim = FileUrl::from_windows_path(r"\\?\C:\a/b").unwrap();
+        assert_eq!(verbatim, drive("/C:/a%2Fb"));
+        assert_eq!(verbatim.to_windows_path(), None);
+        let unc = FileUrl::from_windows_path(r"\\?\UNC\server\share\a").unwrap();
+        assert_eq!(unc.to_windows_path().unwrap(), r"\\server\share\a");
+        assert_eq!(
+            FileUrl::from_windows_path(r"\\server\share").unwrap(),
+            parse("file://server/share")
+        );
+
+        assert!(FileUrl::from_windows_path(r"\\?\Volume{b75e2c83}\x").is_err());
+        assert!(FileUrl::from_windows_path(r"\\.\pipe\x").is_err());
+        assert!(FileUrl::from_windows_path(r"relative\x").is_err());
+        assert!(FileUrl::from_windows_path("C:").is_err());
+        assert!(FileUrl::from_windows_path(r"C:relative").is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! geo URIs ([RFC 5870](https://tools.ietf.org/html/rfc5870))
+//!
+//! ```notrust
+//!     geo:37.786971,-122.399677,12;crs=wgs84;u=35
+//!     \_/ \_______/ \_________/ \/ \___________/
+//!      |      |          |      |        |
+//!   scheme latitude longitude altitude parameters
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::schemes::geo::GeoUri;
+//!
+//! let geo = "geo:37.786971,-122.399677;u=35".parse::<GeoUri<String>>().unwrap();
+//!
+//! assert_eq!(geo.latitude, 37.786971);
+//! assert_eq!(geo.longitude, -122.399677);
+//! assert_eq!(geo.altitude, None);
+//! assert_eq!(geo.params.get("u"), Some(&Some("35".to_string())));
+//! ```
+use nom::{
+    bytes::complete::tag,
+    character::complete::digit1,
+    combinator::{all_consuming, map_res, opt, recognize, verify},
+    sequence::{preceded, tuple},
+};
+
+use crate::parsers::{self, IResult};
+use crate::ParseError;
+use core::hash::Hash;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// The parts of a `geo:` URI
+#[derive(Debug, PartialEq)]
+pub struct GeoUri<T>
+where
+    T: Ord + Hash,
+{
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+    pub params: HashMap<T, Option<T>>,
+}
+
+impl GeoUri<&str> {
+    fn to_owned(&self) -> GeoUri<String> {
+        GeoUri {
+            latitude: self.latitude,
+            longitude: self.longitude,
+            altitude: self.altitude,
+            params: self
+                .params
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), v.map(|v| v.to_string())))
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for GeoUri<String> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match all_consuming(geo_uri)(s) {
+            Ok((_, obj)) => Ok(obj.to_owned()),
+            Err(e) => Err(ParseError::from_nom(e)),
+        }
+    }
+}
+
+/// Parse a decimal coordinate such as `-122.399677`
+fn coordinate(input: &str) -> IResult<&str, f64> {
+    map_res(
+        recognize(tuple((
+            opt(tag("-")),
+            digit1,
+            opt(tuple((tag("."), digit1))),
+        ))),
+        |n: &str| n.parse::<f64>(),
+    )(input)
+}
+
+/// Parses a `geo:` URI
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes::geo;
+/// geo::geo_uri("geo:-48.876667,-123.393333,-10;crs=wgs84");
+/// ```
+pub fn geo_uri(input: &str) -> IResult<&str, GeoUri<&str>> {
+    let (i, _) = verify(parsers::bare_scheme, |s: &str| {
+        s.eq_ignore_ascii_case("geo")
+    })(input)?;
+    let (i, latitude) = verify(coordinate, |l| (-90.0..=90.0).contains(l))(i)?;
+    let (i, longitude) = verify(preceded(tag(","), coordinate), |l| {
+        (-180.0..=180.0).contains(l)
+    })(i)?;
+    let (i, altitude) = opt(preceded(tag(","), coordinate))(i)?;
+    let (i, params) = parsers::params(i)?;
+
+    Ok((
+        i,
+        GeoUri {
+            latitude,
+            longitude,
+            altitude,
+            params,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_geo_uri() {
+        let params = [("crs", Some("wgs84")), ("u", Some("12.5"))]
+            .iter()
+            .cloned()
+            .collect();
+
+        assert_eq!(
+            geo_uri("geo:-48.876667,-123.393333,-10;crs=wgs84;u=12.5"),
+            Ok((
+                "",
+                GeoUri {
+                    latitude: -48.876667,
+                    longitude: -123.393333,
+                    altitude: Some(-10.0),
+                    params,
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn test_geo_uri_out_of_range() {
+        assert!("geo:91,0".parse::<GeoUri<String>>().is_err());
+        assert!("geo:0,180.5".parse::<GeoUri<String>>().is_err());
+        assert!("geo:1".parse::<GeoUri<String>>().is_err());
+    }
+}
@@ -0,0 +1,212 @@
+//! IMAP URLs ([RFC 5092](https://tools.ietf.org/html/rfc5092))
+//!
+//! ```notrust
+//!     imap://fred;AUTH=*@mail.example.com/INBOX;UIDVALIDITY=385759045/;UID=20/;SECTION=1.2
+//!            \__/\_____/ \______________/ \___/\____________________/\______/\___________/
+//!             |     |           |           |            |              |          |
+//!           user  auth        host       mailbox    uidvalidity        uid      section
+//! ```
+//!
+//! A URL without a UID names a mailbox, or with a `?` search the messages
+//! in it matching the search. With a UID it names a message, or a part of
+//! it when a section or partial range is given.
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::schemes::imap::ImapUrl;
+//!
+//! let url = "imap://mail.example.com/INBOX;UIDVALIDITY=785799047/;UID=113330/;SECTION=1.5"
+//!     .parse::<ImapUrl<String>>()
+//!     .unwrap();
+//!
+//! assert_eq!(url.mailbox, Some("INBOX".to_string()));
+//! assert_eq!(url.uid_validity, Some(785799047));
+//! assert_eq!(url.uid, Some(113330));
+//! assert_eq!(url.section, Some("1.5".to_string()));
+//! ```
+use nom::{
+    bytes::complete::{tag, tag_no_case, take_till, take_till1},
+    character::complete::digit1,
+    combinator::{all_consuming, map_res, opt, verify},
+    sequence::{pair, preceded, terminated},
+};
+
+use crate::parsers::{self, IResult};
+use crate::ParseError;
+use core::hash::Hash;
+use std::str::FromStr;
+
+/// The parts of an `imap://` URL
+///
+/// The mailbox is kept as written, percent encoded modified UTF-7.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ImapUrl<T>
+where
+    T: Ord + Hash,
+{
+    pub user: Option<T>,
+    /// The `;AUTH=` mechanism, `*` for any
+    pub auth: Option<T>,
+    pub host: T,
+    pub port: Option<u16>,
+    pub mailbox: Option<T>,
+    pub uid_validity: Option<u32>,
+    pub search: Option<T>,
+    pub uid: Option<u32>,
+    pub section: Option<T>,
+    /// The byte offset and optional length of a `;PARTIAL=` range
+    pub partial: Option<(u32, Option<u32>)>,
+}
+
+impl ImapUrl<&str> {
+    fn to_owned(&self) -> ImapUrl<String> {
+        ImapUrl {
+            user: self.user.map(|u| u.to_string()),
+            auth: self.auth.map(|a| a.to_string()),
+            host: self.host.to_string(),
+            port: self.port,
+            mailbox: self.mailbox.map(|m| m.to_string()),
+            uid_validity: self.uid_validity,
+            search: self.search.map(|s| s.to_string()),
+            uid: self.uid,
+            section: self.section.map(|s| s.to_string()),
+            partial: self.partial,
+        }
+    }
+}
+
+impl FromStr for ImapUrl<String> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match all_consuming(imap_url)(s) {
+            Ok((_, obj)) => Ok(obj.to_owned()),
+            Err(e) => Err(ParseError::from_nom(e)),
+        }
+    }
+}
+
+fn number(input: &str) -> IResult<&str, u32> {
+    map_res(digit1, |d: &str| d.parse::<u32>())(input)
+}
+
+/// A value running up to the next `/`, `;`, `?` or `#`
+fn value(input: &str) -> IResult<&str, &str> {
+    take_till1(|c: char| c == '/' || c == ';' || c == '?' || c == '#' || c.is_whitespace())(input)
+}
+
+/// `user;AUTH=mechanism@`, either part may be left out
+fn credentials(input: &str) -> IResult<&str, (Option<&str>, Option<&str>)> {
+    let (i, userinfo) = terminated(take_till1(|c| c == '@' || c == '/'), tag("@"))(input)?;
+    let (user, auth) = match userinfo.find(';') {
+        Some(n) => match userinfo[n..].get(..6) {
+            Some(auth) if auth.eq_ignore_ascii_case(";AUTH=") => {
+                (&userinfo[..n], Some(&userinfo[n + 6..]))
+            }
+            _ => (userinfo, None),
+        },
+        None => (userinfo, None),
+    };
+    Ok((i, (Some(user).filter(|u| !u.is_empty()), auth)))
+}
+
+/// Parses an `imap://` URL
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes::imap;
+/// imap::imap_url("imap://michael@example.org/INBOX?SUBJECT%20shadows");
+/// ```
+pub fn imap_url(input: &str) -> IResult<&str, ImapUrl<&str>> {
+    let (i, _) = verify(parsers::scheme, |s: &str| s.eq_ignore_ascii_case("imap"))(input)?;
+    let (i, credentials) = opt(credentials)(i)?;
+    let (user, auth) = credentials.unwrap_or((None, None));
+    let (i, (host, port)) = parsers::host_port_combinator(i)?;
+    let (i, _) = opt(tag("/"))(i)?;
+    let (i, mailbox) =
+        take_till(|c: char| c == ';' || c == '?' || c == '#' || c.is_whitespace())(i)?;
+    let (i, uid_validity) = opt(preceded(tag_no_case(";UIDVALIDITY="), number))(i)?;
+    let (i, search) = opt(preceded(
+        tag("?"),
+        take_till(|c: char| c == '#' || c.is_whitespace()),
+    ))(i)?;
+    let (i, uid) = opt(preceded(pair(opt(tag("/")), tag_no_case(";UID=")), number))(i)?;
+    let (i, section) = opt(preceded(tag_no_case("/;SECTION="), value))(i)?;
+    let (i, partial) = opt(preceded(
+        tag_no_case("/;PARTIAL="),
+        pair(number, opt(preceded(tag("."), number))),
+    ))(i)?;
+
+    // `INBOX/;UID=1` names message 1 in `INBOX`
+    let mailbox = match (uid, uid_validity) {
+        (Some(_), None) => mailbox.strip_suffix('/').unwrap_or(mailbox),
+        _ => mailbox,
+    };
+
+    Ok((
+        i,
+        ImapUrl {
+            user,
+            auth,
+            host,
+            port,
+            mailbox: Some(mailbox).filter(|m| !m.is_empty()),
+            uid_validity,
+            search,
+            uid,
+            section,
+            partial,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_imap_message_part() {
+        assert_eq!(
+            imap_url("imap://fred;AUTH=*@h:143/Drafts/2020/;UID=20/;SECTION=1.2/;PARTIAL=0.1024"),
+            Ok((
+                "",
+                ImapUrl {
+                    user: Some("fred"),
+                    auth: Some("*"),
+                    host: "h",
+                    port: Some(143),
+                    mailbox: Some("Drafts/2020"),
+                    uid_validity: None,
+                    search: None,
+                    uid: Some(20),
+                    section: Some("1.2"),
+                    partial: Some((0, Some(1024))),
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn test_imap_search_and_server() {
+        let url = "imap://michael@example.org/INBOX?SUBJECT%20shadows"
+            .parse::<ImapUrl<String>>()
+            .unwrap();
+        assert_eq!(url.user.as_deref(), Some("michael"));
+        assert_eq!(url.search.as_deref(), Some("SUBJECT%20shadows"));
+        assert_eq!(url.uid, None);
+
+        let server = "imap://;AUTH=GSSAPI@example.org"
+            .parse::<ImapUrl<String>>()
+            .unwrap();
+        assert_eq!(
+            (server.user, server.auth.as_deref()),
+            (None, Some("GSSAPI"))
+        );
+        assert_eq!(server.mailbox, None);
+
+        assert!("imap://h/INBOX/;UID=x".parse::<ImapUrl<String>>().is_err());
+        assert!("http://h/INBOX".parse::<ImapUrl<String>>().is_err());
+    }
+}
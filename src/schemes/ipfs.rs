@@ -0,0 +1,202 @@
+//! Content addressed `ipfs://` and `ipns://` URIs
+//!
+//! ```notrust
+//!     ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi/wiki/index.html
+//!     \__/   \_________________________________________________________/\______________/
+//!      |                                 |                                     |
+//!  namespace                            cid                                 subpath
+//! ```
+//!
+//! The CID takes the place of the host but is case sensitive, so it is kept
+//! exactly as written.
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::schemes::ipfs::{ContentPath, Namespace};
+//!
+//! let content = "ipfs://QmXoypizjW3WknFiJnKLwHCnL72vedxjQkDDP1mXWo6uco/wiki/index.html"
+//!     .parse::<ContentPath<String>>()
+//!     .unwrap();
+//!
+//! assert_eq!(content.namespace, Namespace::Ipfs);
+//! assert_eq!(content.cid, "QmXoypizjW3WknFiJnKLwHCnL72vedxjQkDDP1mXWo6uco");
+//! assert_eq!(content.subpath(), "/wiki/index.html");
+//! ```
+use nom::{
+    bytes::complete::take_till1,
+    combinator::{all_consuming, map_opt, verify},
+};
+
+use crate::parsers::{self, IResult};
+use crate::{ParseError, URI};
+use core::hash::Hash;
+use std::str::FromStr;
+
+/// Whether the URI names immutable content or a mutable name
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Namespace {
+    Ipfs,
+    Ipns,
+}
+
+impl Namespace {
+    pub(crate) fn from_scheme(scheme: &str) -> Option<Namespace> {
+        if scheme.eq_ignore_ascii_case("ipfs") {
+            Some(Namespace::Ipfs)
+        } else if scheme.eq_ignore_ascii_case("ipns") {
+            Some(Namespace::Ipns)
+        } else {
+            None
+        }
+    }
+
+    /// CIDs are multibase encoded, IPNS names may also be DNSLink domains
+    fn is_valid_cid(self, cid: &str) -> bool {
+        cid.chars().all(|c| match self {
+            Namespace::Ipfs => c.is_ascii_alphanumeric(),
+            Namespace::Ipns => c.is_ascii_alphanumeric() || c == '.' || c == '-',
+        })
+    }
+}
+
+/// The CID (or IPNS name) and subpath of a content addressed URI
+#[derive(Debug, PartialEq, Eq)]
+pub struct ContentPath<T>
+where
+    T: Ord + Hash,
+{
+    pub namespace: Namespace,
+    pub cid: T,
+    pub path: Vec<T>,
+}
+
+impl<T> ContentPath<T>
+where
+    T: Ord + Hash + AsRef<str>,
+{
+    /// The path below the CID, `/` separated
+    pub fn subpath(&self) -> String {
+        self.path.iter().fold(String::new(), |mut acc, segment| {
+            acc.push('/');
+            acc.push_str(segment.as_ref());
+            acc
+        })
+    }
+}
+
+impl<'a> ContentPath<&'a str> {
+    /// Extract the CID and subpath from an already parsed URI
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    /// use auris::schemes::ipfs::ContentPath;
+    ///
+    /// let uri = "ipns://docs.ipfs.tech/how-to".parse::<URI<String>>().unwrap();
+    /// let content = ContentPath::from_uri(&uri).unwrap();
+    /// assert_eq!(content.cid, "docs.ipfs.tech");
+    /// ```
+    pub fn from_uri(uri: &'a URI<String>) -> Option<ContentPath<&'a str>> {
+        let namespace = Namespace::from_scheme(&uri.scheme)?;
+        let authority = &uri.authority;
+        if authority.userinfo.is_some()
+            || authority.port.is_some()
+            || authority.host.is_empty()
+            || !namespace.is_valid_cid(&authority.host)
+        {
+            return None;
+        }
+        Some(ContentPath {
+            namespace,
+            cid: &authority.host,
+            path: uri
+                .path
+                .as_ref()
+                .map(|p| p.iter().map(|s| s.as_str()).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    fn to_owned(&self) -> ContentPath<String> {
+        ContentPath {
+            namespace: self.namespace,
+            cid: self.cid.to_string(),
+            path: self.path.iter().map(|p| (*p).to_string()).collect(),
+        }
+    }
+}
+
+impl FromStr for ContentPath<String> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match all_consuming(content_path)(s) {
+            Ok((_, obj)) => Ok(obj.to_owned()),
+            Err(e) => Err(ParseError::from_nom(e)),
+        }
+    }
+}
+
+/// Parses an `ipfs://` or `ipns://` URI
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes::ipfs;
+/// ipfs::content_path("ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi/a");
+/// ```
+pub fn content_path(input: &str) -> IResult<&str, ContentPath<&str>> {
+    let (i, namespace) = map_opt(parsers::scheme, Namespace::from_scheme)(input)?;
+    let (i, cid) = verify(
+        take_till1(|c: char| c == '/' || c == '?' || c == '#' || c.is_whitespace()),
+        |cid: &str| namespace.is_valid_cid(cid),
+    )(i)?;
+    let (i, path) = parsers::path(i)?;
+
+    Ok((
+        i,
+        ContentPath {
+            namespace,
+            cid,
+            path,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_content_path() {
+        assert_eq!(
+            content_path(
+                "ipns://k51qzi5uqu5dlvj2baxnqndepeb86cbk3ng7n3i46uzyxzyqj2xjonzllnv0v8/blog"
+            ),
+            Ok((
+                "",
+                ContentPath {
+                    namespace: Namespace::Ipns,
+                    cid: "k51qzi5uqu5dlvj2baxnqndepeb86cbk3ng7n3i46uzyxzyqj2xjonzllnv0v8",
+                    path: vec!["blog"],
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn test_cid_case_is_preserved() {
+        let uri = "ipfs://QmXoypizjW3WknFiJnKLwHCnL72vedxjQkDDP1mXWo6uco"
+            .parse::<URI<String>>()
+            .unwrap();
+        let content = ContentPath::from_uri(&uri).unwrap();
+        assert_eq!(
+            content.cid,
+            "QmXoypizjW3WknFiJnKLwHCnL72vedxjQkDDP1mXWo6uco"
+        );
+        assert_eq!(content.subpath(), "");
+        assert!("ipfs://not.a.cid/".parse::<ContentPath<String>>().is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! LDAP URLs ([RFC 4516](https://tools.ietf.org/html/rfc4516))
+//!
+//! ```notrust
+//!     ldap://[2001:db8::7]/c=GB?objectClass?one?(cn=Babs)
+//!     \__/   \___________/ \__/ \_________/ \_/ \_______/
+//!      |           |        |        |       |      |
+//!   scheme        host      dn  attributes scope  filter
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::schemes::ldap::{LdapUrl, Scope};
+//!
+//! let url = "ldap://[2001:db8::7]/c=GB?objectClass?one"
+//!     .parse::<LdapUrl<String>>()
+//!     .unwrap();
+//!
+//! assert_eq!(url.dn, "c=GB");
+//! assert_eq!(url.attributes, vec!["objectClass"]);
+//! assert_eq!(url.scope, Some(Scope::One));
+//! ```
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, tag_no_case, take_till},
+    combinator::{all_consuming, map, opt, rest, verify},
+    sequence::preceded,
+};
+
+use crate::parsers::{self, IResult};
+use crate::{percent, ParseError};
+use core::hash::Hash;
+use std::str::FromStr;
+
+/// Search scope of an LDAP URL
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Scope {
+    Base,
+    One,
+    Sub,
+}
+
+/// The parts of an LDAP URL
+///
+/// Optional parts that were left out of the URL are `None` (or empty), the
+/// RFC defaults are a `Base` scope and a `(objectClass=*)` filter.
+///
+/// `FromStr` percent decodes the DN and filter, so `cn=J%C3%BCrgen%2C%20Jr`
+/// becomes `cn=Jürgen, Jr`. [`ldap_url`] borrows every part as it is
+/// written, still percent encoded.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LdapUrl<T>
+where
+    T: Ord + Hash,
+{
+    pub scheme: T,
+    pub host: T,
+    pub port: Option<u16>,
+    pub dn: T,
+    pub attributes: Vec<T>,
+    pub scope: Option<Scope>,
+    pub filter: Option<T>,
+    pub extensions: Vec<T>,
+}
+
+impl LdapUrl<&str> {
+    fn to_owned(&self) -> LdapUrl<String> {
+        LdapUrl {
+            scheme: self.scheme.to_string(),
+            host: self.host.to_string(),
+            port: self.port,
+            dn: percent::decode(self.dn).into_owned(),
+            attributes: self.attributes.iter().map(|a| (*a).to_string()).collect(),
+            scope: self.scope,
+            filter: self.filter.map(|f| percent::decode(f).into_owned()),
+            extensions: self.extensions.iter().map(|e| (*e).to_string()).collect(),
+        }
+    }
+}
+
+impl FromStr for LdapUrl<String> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match all_consuming(ldap_url)(s) {
+            Ok((_, obj)) => Ok(obj.to_owned()),
+            Err(e) => Err(ParseError::from_nom(e)),
+        }
+    }
+}
+
+fn scope(input: &str) -> IResult<&str, Scope> {
+    alt((
+        map(tag_no_case("base"), |_| Scope::Base),
+        map(tag_no_case("one"), |_| Scope::One),
+        map(tag_no_case("sub"), |_| Scope::Sub),
+    ))(input)
+}
+
+/// Split a comma separated list, dropping empty entries
+fn list(input: Option<&str>) -> Vec<&str> {
+    input
+        .map(|l| l.split(',').filter(|e| !e.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// Parses an `ldap://` or `ldaps://` URL
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes::ldap;
+/// ldap::ldap_url("ldap://ldap.example.com/o=University?cn,mail?sub?(uid=bob)");
+/// ```
+pub fn ldap_url(input: &str) -> IResult<&str, LdapUrl<&str>> {
+    let field = |i| take_till(|c| c == '?')(i);
+
+    let (i, scheme) = verify(parsers::scheme, |s: &str| {
+        s.eq_ignore_ascii_case("ldap") || s.eq_ignore_ascii_case("ldaps")
+    })(input)?;
+    let (i, (host, port)) = parsers::host_port_combinator(i)?;
+    let (i, dn) = opt(preceded(tag("/"), field))(i)?;
+    let (i, attributes) = opt(preceded(tag("?"), field))(i)?;
+    let (i, scope) = opt(preceded(tag("?"), opt(scope)))(i)?;
+    let (i, filter) = opt(preceded(tag("?"), field))(i)?;
+    let (i, extensions) = opt(preceded(tag("?"), rest))(i)?;
+
+    Ok((
+        i,
+        LdapUrl {
+            scheme,
+            host,
+            port,
+            dn: dn.unwrap_or(""),
+            attributes: list(attributes),
+            scope: scope.flatten(),
+            filter: filter.filter(|f| !f.is_empty()),
+            extensions: list(extensions),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ldap_url() {
+        assert_eq!(
+            ldap_url("ldaps://ldap.example.com:636/o=Example?cn,mail?sub?(uid=bob)?!x-ext"),
+            Ok((
+                "",
+                LdapUrl {
+                    scheme: "ldaps",
+                    host: "ldap.example.com",
+                    port: Some(636),
+                    dn: "o=Example",
+                    attributes: vec!["cn", "mail"],
+                    scope: Some(Scope::Sub),
+                    filter: Some("(uid=bob)"),
+                    extensions: vec!["!x-ext"],
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn test_ldap_url_defaults() {
+        assert_eq!(
+            ldap_url("ldap:///??sub"),
+            Ok((
+                "",
+                LdapUrl {
+                    scheme: "ldap",
+                    host: "",
+                    port: None,
+                    dn: "",
+                    attributes: vec![],
+                    scope: Some(Scope::Sub),
+                    filter: None,
+                    extensions: vec![],
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn test_from_str_decodes_dn_and_filter() {
+        let url = "ldap://h/cn=J%C3%BCrgen%2C%20Jr,o=Example?cn??(cn=J%C3%BCrgen*)"
+            .parse::<LdapUrl<String>>()
+            .unwrap();
+        assert_eq!(url.dn, "cn=Jürgen, Jr,o=Example");
+        assert_eq!(url.filter.as_deref(), Some("(cn=Jürgen*)"));
+        assert_eq!(url.attributes, vec!["cn"]);
+    }
+
+    #[test]
+    fn test_not_ldap() {
+        assert!("http://example.com".parse::<LdapUrl<String>>().is_err());
+        assert!("ldap://host/dn?a?bogus".parse::<LdapUrl<String>>().is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//! Scheme specific views over URIs
+//!
+//! Some schemes give extra structure to the generic URI components, these
+//! modules expose that structure as typed values.
+pub mod bitcoin;
+pub mod did;
+pub mod file;
+pub mod geo;
+pub mod imap;
+pub mod ipfs;
+pub mod ldap;
+pub mod news;
+pub mod opaque;
+pub mod sip;
+
+/// Well known schemes and their default ports
+pub const KNOWN_SCHEMES: &[(&str, Option<u16>)] = &[
+    ("http", Some(80)),
+    ("https", Some(443)),
+    ("ws", Some(80)),
+    ("wss", Some(443)),
+    ("ftp", Some(21)),
+    ("ftps", Some(990)),
+    ("sftp", Some(22)),
+    ("ssh", Some(22)),
+    ("git", Some(9418)),
+    ("telnet", Some(23)),
+    ("ldap", Some(389)),
+    ("ldaps", Some(636)),
+    ("sip", Some(5060)),
+    ("sips", Some(5061)),
+    ("imap", Some(143)),
+    ("imaps", Some(993)),
+    ("pop", Some(110)),
+    ("smtp", Some(25)),
+    ("nntp", Some(119)),
+    ("news", None),
+    ("postgres", Some(5432)),
+    ("postgresql", Some(5432)),
+    ("mysql", Some(3306)),
+    ("mongodb", Some(27017)),
+    ("redis", Some(6379)),
+    ("amqp", Some(5672)),
+    ("amqps", Some(5671)),
+    ("mqtt", Some(1883)),
+    ("file", None),
+    ("mailto", None),
+    ("tel", None),
+    ("urn", None),
+    ("data", None),
+    ("geo", None),
+    ("did", None),
+    ("ipfs", None),
+    ("ipns", None),
+    ("bitcoin", None),
+];
+
+/// The default port of a well known scheme
+///
+/// A compound scheme that isn't well known itself gets the default port of
+/// its transport.
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes;
+/// assert_eq!(schemes::default_port("HTTPS"), Some(443));
+/// assert_eq!(schemes::default_port("mailto"), None);
+/// assert_eq!(schemes::default_port("git+ssh"), Some(22));
+/// ```
+pub fn default_port(scheme: &str) -> Option<u16> {
+    match KNOWN_SCHEMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(scheme))
+    {
+        Some((_, port)) => *port,
+        None => split_compound(scheme).and_then(|(_, transport)| default_port(transport)),
+    }
+}
+
+/// Split a compound scheme such as `git+ssh` or `svn+https` into the
+/// protocol and the transport it runs over
+///
+/// Only the first `+` separates the parts, so the transport of
+/// `a+b+c` is `b+c`. Schemes without a `+`, or with an empty part, aren't
+/// compound.
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes;
+/// assert_eq!(schemes::split_compound("git+ssh"), Some(("git", "ssh")));
+/// assert_eq!(schemes::split_compound("https"), None);
+/// assert_eq!(schemes::split_compound("git+"), None);
+/// ```
+pub fn split_compound(scheme: &str) -> Option<(&str, &str)> {
+    scheme
+        .split_once('+')
+        .filter(|(protocol, transport)| !protocol.is_empty() && !transport.is_empty())
+}
+
+/// Join a protocol and a transport into a compound scheme, the inverse of
+/// [`split_compound`]
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes;
+/// assert_eq!(schemes::join_compound("svn", "https"), "svn+https");
+/// ```
+pub fn join_compound(protocol: &str, transport: &str) -> String {
+    format!("{}+{}", protocol, transport)
+}
+
+/// Levenshtein distance between two short ASCII case insensitive strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<u8> = b.bytes().map(|c| c.to_ascii_lowercase()).collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.bytes().map(|c| c.to_ascii_lowercase()).enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Suggest a well known scheme when `scheme` looks like a typo of one
+///
+/// Known schemes themselves get no suggestion.
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes;
+/// assert_eq!(schemes::suggest("htp"), Some("http"));
+/// assert_eq!(schemes::suggest("htttps"), Some("https"));
+/// assert_eq!(schemes::suggest("postgre"), Some("postgres"));
+/// assert_eq!(schemes::suggest("https"), None);
+/// assert_eq!(schemes::suggest("spotify"), None);
+/// ```
+pub fn suggest(scheme: &str) -> Option<&'static str> {
+    if scheme.is_empty()
+        || KNOWN_SCHEMES
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case(scheme))
+    {
+        return None;
+    }
+    // short schemes only tolerate a single edit
+    let max_distance = if scheme.len() <= 4 { 1 } else { 2 };
+    KNOWN_SCHEMES
+        .iter()
+        .map(|(name, _)| (edit_distance(scheme, name), *name))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
@@ -0,0 +1,196 @@
+//! Usenet `news:` and `nntp://` URIs ([RFC 5538](https://tools.ietf.org/html/rfc5538))
+//!
+//! ```notrust
+//!     news:comp.lang.rust      news:1234@example.com
+//!          \____________/           \______________/
+//!                |                         |
+//!            newsgroup                 message-id
+//!
+//!     nntp://news.example.com:119/comp.lang.rust/42
+//!            \______________/ \_/ \____________/ \/
+//!                    |         |         |        |
+//!                  host      port    newsgroup  article
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::schemes::news::NewsUrl;
+//!
+//! let url = "nntp://news.example.com/comp.lang.rust/42".parse::<NewsUrl<String>>().unwrap();
+//! assert_eq!(url.group, Some("comp.lang.rust".to_string()));
+//! assert_eq!(url.article, Some(42));
+//!
+//! let url = "news:comp.lang.rust".parse::<NewsUrl<String>>().unwrap();
+//! assert_eq!(url.group, Some("comp.lang.rust".to_string()));
+//! assert_eq!(url.host, "");
+//!
+//! let url = "news:1234@example.com".parse::<NewsUrl<String>>().unwrap();
+//! assert_eq!(url.message_id, Some("1234@example.com".to_string()));
+//! ```
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till1},
+    character::complete::digit1,
+    combinator::{all_consuming, map_res, opt, verify},
+    sequence::preceded,
+};
+
+use crate::parsers::{self, IResult};
+use crate::ParseError;
+use core::hash::Hash;
+use std::str::FromStr;
+
+/// The parts of a `news:` or `nntp://` URI
+///
+/// A `news:` URI names either a newsgroup (possibly with `*` wildcards) or
+/// an article by its message-id, and only optionally a server. An `nntp://`
+/// URI always has a server and a newsgroup, and optionally an article
+/// number within the group.
+#[derive(Debug, PartialEq, Eq)]
+pub struct NewsUrl<T>
+where
+    T: Ord + Hash,
+{
+    pub scheme: T,
+    /// Empty when no server is given
+    pub host: T,
+    pub port: Option<u16>,
+    pub group: Option<T>,
+    pub article: Option<u64>,
+    pub message_id: Option<T>,
+}
+
+impl NewsUrl<&str> {
+    fn to_owned(&self) -> NewsUrl<String> {
+        NewsUrl {
+            scheme: self.scheme.to_string(),
+            host: self.host.to_string(),
+            port: self.port,
+            group: self.group.map(|g| g.to_string()),
+            article: self.article,
+            message_id: self.message_id.map(|m| m.to_string()),
+        }
+    }
+}
+
+impl FromStr for NewsUrl<String> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match all_consuming(news_url)(s) {
+            Ok((_, obj)) => Ok(obj.to_owned()),
+            Err(e) => Err(ParseError::from_nom(e)),
+        }
+    }
+}
+
+/// A newsgroup or message-id, up to the end of the path
+fn group_or_message_id(input: &str) -> IResult<&str, &str> {
+    take_till1(|c: char| c == '/' || c == '?' || c == '#' || c.is_whitespace())(input)
+}
+
+/// Message-ids always contain an `@`, newsgroup names never do
+fn news_target<'a>(scheme: &'a str, name: Option<&'a str>) -> NewsUrl<&'a str> {
+    let is_message_id = name.is_some_and(|n| n.contains('@'));
+    NewsUrl {
+        scheme,
+        host: "",
+        port: None,
+        group: name.filter(|_| !is_message_id),
+        article: None,
+        message_id: name.filter(|_| is_message_id),
+    }
+}
+
+fn news_without_server(input: &str) -> IResult<&str, NewsUrl<&str>> {
+    let (i, scheme) = verify(parsers::bare_scheme, |s: &str| {
+        s.eq_ignore_ascii_case("news")
+    })(input)?;
+    let (i, name) = group_or_message_id(i)?;
+    Ok((i, news_target(scheme, Some(name))))
+}
+
+fn news_with_server(input: &str) -> IResult<&str, NewsUrl<&str>> {
+    let (i, scheme) = verify(parsers::scheme, |s: &str| s.eq_ignore_ascii_case("news"))(input)?;
+    let (i, (host, port)) = parsers::host_port_combinator(i)?;
+    let (i, name) = opt(preceded(tag("/"), group_or_message_id))(i)?;
+    Ok((
+        i,
+        NewsUrl {
+            host,
+            port,
+            ..news_target(scheme, name)
+        },
+    ))
+}
+
+fn nntp(input: &str) -> IResult<&str, NewsUrl<&str>> {
+    let (i, scheme) = verify(parsers::scheme, |s: &str| s.eq_ignore_ascii_case("nntp"))(input)?;
+    let (i, (host, port)) = verify(parsers::host_port_combinator, |(host, _)| !host.is_empty())(i)?;
+    let (i, group) = preceded(
+        tag("/"),
+        verify(group_or_message_id, |g: &str| !g.contains('@')),
+    )(i)?;
+    let (i, article) = opt(preceded(
+        tag("/"),
+        map_res(digit1, |d: &str| d.parse::<u64>()),
+    ))(i)?;
+    Ok((
+        i,
+        NewsUrl {
+            scheme,
+            host,
+            port,
+            group: Some(group),
+            article,
+            message_id: None,
+        },
+    ))
+}
+
+/// Parses a `news:` or `nntp://` URI
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes::news;
+/// news::news_url("news://news.example.com/comp.lang.*");
+/// ```
+pub fn news_url(input: &str) -> IResult<&str, NewsUrl<&str>> {
+    alt((nntp, news_with_server, news_without_server))(input)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_news_with_server() {
+        assert_eq!(
+            news_url("news://news.example.com:563/comp.lang.*"),
+            Ok((
+                "",
+                NewsUrl {
+                    scheme: "news",
+                    host: "news.example.com",
+                    port: Some(563),
+                    group: Some("comp.lang.*"),
+                    article: None,
+                    message_id: None,
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn test_nntp_article() {
+        let url = "nntp://h/alt.test/12".parse::<NewsUrl<String>>().unwrap();
+        assert_eq!(url.group.as_deref(), Some("alt.test"));
+        assert_eq!(url.article, Some(12));
+        assert!("nntp://h/alt.test/x".parse::<NewsUrl<String>>().is_err());
+        assert!("nntp://h/".parse::<NewsUrl<String>>().is_err());
+        assert!("nntp:alt.test".parse::<NewsUrl<String>>().is_err());
+        assert!("news:".parse::<NewsUrl<String>>().is_err());
+    }
+}
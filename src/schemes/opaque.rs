@@ -0,0 +1,136 @@
+//! Vendor schemes kept exactly as written
+//!
+//! ```notrust
+//!     spotify:track:6rqhFgbbKwnb9MLmUQDhG6#0:30
+//!     \_____/ \_____________________________/ \__/
+//!        |                  |                   |
+//!     scheme               body              fragment
+//! ```
+//!
+//! Apps register schemes like `spotify:`, `steam://` or `vscode://` whose
+//! structure only the app understands. [`URI`](crate::URI) parses most of
+//! them, but normalizes the query on the way. An [`OpaqueUri`] splits off
+//! only the scheme and fragment, so it displays back byte for byte.
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::schemes::opaque::OpaqueUri;
+//!
+//! let uri = "x-vendor:open?b&a=1#top".parse::<OpaqueUri<String>>().unwrap();
+//!
+//! assert_eq!(uri.scheme, "x-vendor");
+//! assert_eq!(uri.body, "open?b&a=1");
+//! assert_eq!("x-vendor:open?b&a=1#top", format!("{}", uri));
+//! ```
+use nom::{
+    bytes::complete::take_till,
+    combinator::{all_consuming, opt, verify},
+};
+
+use crate::parsers::{self, IResult};
+use crate::ParseError;
+use core::hash::Hash;
+use std::fmt;
+use std::str::FromStr;
+
+/// A URI split into its scheme, everything up to the fragment, and the
+/// fragment
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OpaqueUri<T>
+where
+    T: Ord + Hash,
+{
+    pub scheme: T,
+    /// Everything between the `:` and the `#`, including any `//`
+    pub body: T,
+    pub fragment: Option<T>,
+}
+
+impl OpaqueUri<&str> {
+    fn to_owned(&self) -> OpaqueUri<String> {
+        OpaqueUri {
+            scheme: self.scheme.to_string(),
+            body: self.body.to_string(),
+            fragment: self.fragment.map(|f| f.to_string()),
+        }
+    }
+}
+
+impl<T> OpaqueUri<T>
+where
+    T: Ord + Hash + AsRef<str>,
+{
+    /// Whether the body starts with an authority, as in `steam://run/440`
+    pub fn has_authority(&self) -> bool {
+        self.body.as_ref().starts_with("//")
+    }
+}
+
+impl FromStr for OpaqueUri<String> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match all_consuming(opaque_uri)(s) {
+            Ok((_, obj)) => Ok(obj.to_owned()),
+            Err(e) => Err(ParseError::from_nom(e)),
+        }
+    }
+}
+
+impl<T> fmt::Display for OpaqueUri<T>
+where
+    T: Ord + Hash + fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.scheme, self.body)?;
+        if let Some(fragment) = self.fragment.as_ref() {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses any URI into its scheme, body and fragment
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes::opaque;
+/// opaque::opaque_uri("steam://run/440");
+/// ```
+pub fn opaque_uri(input: &str) -> IResult<&str, OpaqueUri<&str>> {
+    let (i, scheme) = verify(parsers::bare_scheme, parsers::is_valid_scheme)(input)?;
+    let (i, body) = take_till(|c: char| c == '#' || c.is_whitespace())(i)?;
+    let (i, fragment) = opt(parsers::fragment)(i)?;
+
+    Ok((
+        i,
+        OpaqueUri {
+            scheme,
+            body,
+            fragment,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_opaque_round_trip() {
+        for input in &[
+            "spotify:track:6rqhFgbbKwnb9MLmUQDhG6",
+            "steam://run/440",
+            "magnet:?xt=urn:btih:c12fe1&dn=a&dn=b&tr",
+            "x:",
+            "x:#",
+        ] {
+            let uri = input.parse::<OpaqueUri<String>>().unwrap();
+            assert_eq!(uri.to_string(), *input);
+        }
+        assert!("1x:a".parse::<OpaqueUri<String>>().is_err());
+        assert!("no-scheme".parse::<OpaqueUri<String>>().is_err());
+    }
+}
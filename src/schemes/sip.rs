@@ -0,0 +1,239 @@
+//! SIP URIs ([RFC 3261](https://tools.ietf.org/html/rfc3261#section-19.1))
+//!
+//! ```notrust
+//!     sip:alice:secret@atlanta.com:5060;transport=tcp;lr?subject=project
+//!     \_/ \______________________/     \_______________/ \_____________/
+//!      |              |                        |                |
+//!   scheme        authority               parameters         headers
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::schemes::sip::SipUri;
+//!
+//! let uri = "sip:alice@atlanta.com;transport=tcp;lr"
+//!     .parse::<SipUri<String>>()
+//!     .unwrap();
+//!
+//! assert_eq!(uri.authority.host, "atlanta.com");
+//! assert_eq!(uri.params.get("transport"), Some(&Some("tcp".to_string())));
+//! assert_eq!(uri.params.get("lr"), Some(&None));
+//! ```
+use nom::{
+    bytes::complete::{tag, take_till1},
+    combinator::{all_consuming, opt, verify},
+    sequence::terminated,
+};
+
+use crate::parsers::{self, IResult};
+use crate::{Authority, ParseError, QueryMap, UserInfo};
+use core::hash::Hash;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// The parts of a `sip:` or `sips:` URI
+#[derive(Debug, PartialEq, Eq)]
+pub struct SipUri<T>
+where
+    T: Ord + Hash,
+{
+    pub scheme: T,
+    pub authority: Authority<T>,
+    pub params: HashMap<T, Option<T>>,
+    pub headers: QueryMap<T, T>,
+}
+
+impl SipUri<&str> {
+    fn to_owned(&self) -> SipUri<String> {
+        SipUri {
+            scheme: self.scheme.to_string(),
+            authority: self.authority.to_owned(),
+            params: self
+                .params
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), v.map(|v| v.to_string())))
+                .collect(),
+            headers: self
+                .headers
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for SipUri<String> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match all_consuming(sip_uri)(s) {
+            Ok((_, obj)) => Ok(obj.to_owned()),
+            Err(e) => Err(ParseError::from_nom(e)),
+        }
+    }
+}
+
+/// Parse the `user[:password]@` part, SIP users are much less restricted
+/// than the generic userinfo.
+///
+/// A user may contain `;` and `?`, as in `sip:alice;day=tuesday@atlanta.com`,
+/// so the userinfo is everything up to an `@`, which parameters and headers
+/// can only hold escaped.
+fn userinfo(input: &str) -> IResult<&str, UserInfo<&str>> {
+    let (i, info) = terminated(take_till1(|c| c == '@'), tag("@"))(input)?;
+    let (password, user) = take_till1(|c| c == ':')(info)?;
+    match password.strip_prefix(':') {
+        Some(password) => Ok((i, UserInfo::UserAndPassword(user, password))),
+        None => Ok((i, UserInfo::User(user))),
+    }
+}
+
+fn host(input: &str) -> IResult<&str, &str> {
+    if input.starts_with('[') {
+        parsers::host(input)
+    } else {
+        take_till1(|c| c == ':' || c == ';' || c == '?' || c == '@')(input)
+    }
+}
+
+/// Parses a `sip:` or `sips:` URI
+///
+/// # Examples
+///
+/// ```
+/// use auris::schemes::sip;
+/// sip::sip_uri("sips:bob:pw@biloxi.com:5061;maddr=239.255.255.1?priority=urgent");
+/// ```
+pub fn sip_uri(input: &str) -> IResult<&str, SipUri<&str>> {
+    let (i, scheme) = verify(parsers::bare_scheme, |s: &str| {
+        s.eq_ignore_ascii_case("sip") || s.eq_ignore_ascii_case("sips")
+    })(input)?;
+    let (i, userinfo) = opt(userinfo)(i)?;
+    let (i, host) = host(i)?;
+    let (i, port) = opt(parsers::port)(i)?;
+    let (i, params) = parsers::params(i)?;
+    let (i, headers) = opt(parsers::query)(i)?;
+
+    Ok((
+        i,
+        SipUri {
+            scheme,
+            authority: Authority {
+                host,
+                userinfo,
+                port,
+            },
+            params,
+            headers: headers.unwrap_or_default(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sip_uri() {
+        let params = [("maddr", Some("239.255.255.1")), ("lr", None)]
+            .iter()
+            .cloned()
+            .collect();
+        let headers = [("priority", "urgent")].iter().cloned().collect();
+
+        assert_eq!(
+            sip_uri("sips:bob:pw@biloxi.com:5061;maddr=239.255.255.1;lr?priority=urgent"),
+            Ok((
+                "",
+                SipUri {
+                    scheme: "sips",
+                    authority: Authority {
+                        host: "biloxi.com",
+                        userinfo: Some(UserInfo::UserAndPassword("bob", "pw")),
+                        port: Some(5061),
+                    },
+                    params,
+                    headers,
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn test_sip_uri_without_user() {
+        let (_, uri) = sip_uri("sip:[2001:db8::10]:5070;transport=udp").unwrap();
+        assert_eq!(uri.authority.host, "[2001:db8::10]");
+        assert_eq!(uri.authority.userinfo, None);
+        assert_eq!(uri.authority.port, Some(5070));
+        assert!("http://example.com".parse::<SipUri<String>>().is_err());
+    }
+
+    #[test]
+    fn test_rfc_3261_examples() {
+        let parse = |s: &str| s.parse::<SipUri<String>>().unwrap();
+
+        let uri = parse("sip:alice@atlanta.com");
+        assert_eq!(uri.authority.host, "atlanta.com");
+        assert_eq!(
+            uri.authority.userinfo,
+            Some(UserInfo::User("alice".to_string()))
+        );
+
+        let uri = parse("sip:alice:secretword@atlanta.com;transport=tcp");
+        assert_eq!(
+            uri.authority.userinfo,
+            Some(UserInfo::UserAndPassword(
+                "alice".to_string(),
+                "secretword".to_string()
+            ))
+        );
+        assert_eq!(uri.params["transport"], Some("tcp".to_string()));
+
+        let uri = parse("sips:alice@atlanta.com?subject=project%20x&priority=urgent");
+        assert_eq!(uri.headers["subject"], "project%20x");
+        assert_eq!(uri.headers["priority"], "urgent");
+
+        let uri = parse("sip:+1-212-555-1212:1234@gateway.com;user=phone");
+        assert_eq!(
+            uri.authority.userinfo,
+            Some(UserInfo::UserAndPassword(
+                "+1-212-555-1212".to_string(),
+                "1234".to_string()
+            ))
+        );
+        assert_eq!(uri.authority.host, "gateway.com");
+        assert_eq!(uri.params["user"], Some("phone".to_string()));
+
+        let uri = parse("sips:1212@gateway.com");
+        assert_eq!(
+            uri.authority.userinfo,
+            Some(UserInfo::User("1212".to_string()))
+        );
+
+        let uri = parse("sip:alice@192.0.2.4");
+        assert_eq!(uri.authority.host, "192.0.2.4");
+
+        let uri = parse("sip:atlanta.com;method=REGISTER?to=alice%40atlanta.com");
+        assert_eq!(uri.authority.userinfo, None);
+        assert_eq!(uri.authority.host, "atlanta.com");
+        assert_eq!(uri.params["method"], Some("REGISTER".to_string()));
+        assert_eq!(uri.headers["to"], "alice%40atlanta.com");
+
+        let uri = parse("sip:alice;day=tuesday@atlanta.com");
+        assert_eq!(
+            uri.authority.userinfo,
+            Some(UserInfo::User("alice;day=tuesday".to_string()))
+        );
+        assert_eq!(uri.authority.host, "atlanta.com");
+        assert!(uri.params.is_empty());
+    }
+
+    #[test]
+    fn test_sip_uri_needs_a_host() {
+        assert!("sip:".parse::<SipUri<String>>().is_err());
+        assert!("sip:;lr".parse::<SipUri<String>>().is_err());
+        assert!("sip:alice@".parse::<SipUri<String>>().is_err());
+        assert!("sip:@atlanta.com".parse::<SipUri<String>>().is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! Checks for URIs built to mislead the people reading them
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::URI;
+//! use auris::security::Finding;
+//! use auris::visit::Component;
+//!
+//! // shows as "https://example.com/exe.pdf" once the override is applied
+//! let uri = "https://example.com/%E2%80%AEfdp.exe".parse::<URI<String>>().unwrap();
+//!
+//! assert_eq!(
+//!     uri.security_findings(),
+//!     vec![Finding::BidiControl {
+//!         component: Component::PathSegment,
+//!         character: '\u{202e}',
+//!     }]
+//! );
+//! ```
+use crate::iri::is_bidi_control;
+use crate::percent;
+use crate::visit::Component;
+use crate::{UserInfo, URI};
+use std::fmt;
+
+/// Something suspicious about a URI
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Finding {
+    /// A bidirectional control character, which can reorder how the rest
+    /// of the URI is displayed, in a component once percent decoded
+    BidiControl {
+        component: Component,
+        character: char,
+    },
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::BidiControl {
+                component,
+                character,
+            } => write!(
+                f,
+                "bidirectional control character U+{:04X} in {:?}",
+                u32::from(*character),
+                component
+            ),
+        }
+    }
+}
+
+fn check_bidi(findings: &mut Vec<Finding>, component: Component, value: &str) {
+    for character in percent::decode(value)
+        .chars()
+        .filter(|c| is_bidi_control(*c))
+    {
+        findings.push(Finding::BidiControl {
+            component,
+            character,
+        });
+    }
+}
+
+impl URI<String> {
+    /// Everything suspicious about the URI, in component order
+    ///
+    /// Components are checked after percent decoding, as that is how they
+    /// are shown to users.
+    pub fn security_findings(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        match self.authority.userinfo.as_ref() {
+            Some(UserInfo::User(user)) => check_bidi(&mut findings, Component::User, user),
+            Some(UserInfo::UserAndPassword(user, password)) => {
+                check_bidi(&mut findings, Component::User, user);
+                check_bidi(&mut findings, Component::Password, password);
+            }
+            None => (),
+        }
+        check_bidi(&mut findings, Component::Host, &self.authority.host);
+        for segment in self.path.iter().flatten() {
+            check_bidi(&mut findings, Component::PathSegment, segment);
+        }
+        for (k, v) in self.qs.iter().flatten() {
+            check_bidi(&mut findings, Component::QueryKey, k);
+            check_bidi(&mut findings, Component::QueryValue, v);
+        }
+        if let Some(fragment) = self.fragment.as_ref() {
+            check_bidi(&mut findings, Component::Fragment, fragment);
+        }
+        findings
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bidi_findings() {
+        let uri = "http://h/a\u{2067}b/c?k=%E2%81%A6#%D8%9C"
+            .parse::<URI<String>>()
+            .unwrap();
+        assert_eq!(
+            uri.security_findings(),
+            vec![
+                Finding::BidiControl {
+                    component: Component::PathSegment,
+                    character: '\u{2067}',
+                },
+                Finding::BidiControl {
+                    component: Component::QueryValue,
+                    character: '\u{2066}',
+                },
+                Finding::BidiControl {
+                    component: Component::Fragment,
+                    character: '\u{061c}',
+                },
+            ]
+        );
+        assert_eq!(
+            uri.security_findings()[0].to_string(),
+            "bidirectional control character U+2067 in PathSegment"
+        );
+        let clean = "http://h/%D7%A9%D7%9C%D7%95%D7%9D?q=%E2%80%8B"
+            .parse::<URI<String>>()
+            .unwrap();
+        assert!(clean.security_findings().is_empty());
+    }
+
+    #[test]
+    fn test_bidi_in_userinfo() {
+        let uri = "http://%E2%80%AEadmin:pw%E2%81%A9@h/"
+            .parse::<URI<String>>()
+            .unwrap();
+        assert_eq!(
+            uri.security_findings(),
+            vec![
+                Finding::BidiControl {
+                    component: Component::User,
+                    character: '\u{202e}',
+                },
+                Finding::BidiControl {
+                    component: Component::Password,
+                    character: '\u{2069}',
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,483 @@
+//! URI templates ([RFC 6570](https://tools.ietf.org/html/rfc6570)), up to
+//! level 4
+//!
+//! ```notrust
+//!     https://api.github.com/repos/{owner}/{repo}/issues{?state,labels*}
+//!                                  \_____/                \____________/
+//!                                     |                         |
+//!                                expression          query expansion, with
+//!                                                    `labels` exploded
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::template::{UriTemplate, Value};
+//! use std::collections::HashMap;
+//!
+//! let template = "https://api.example.com/repos/{owner}/{repo}/issues{?state,labels*}"
+//!     .parse::<UriTemplate>()
+//!     .unwrap();
+//!
+//! let mut vars = HashMap::new();
+//! vars.insert("owner", Value::from("rust-lang"));
+//! vars.insert("repo", Value::from("rust"));
+//! vars.insert("labels", Value::from(vec!["A-parser", "good first issue"]));
+//!
+//! assert_eq!(
+//!     template.expand(&vars),
+//!     "https://api.example.com/repos/rust-lang/rust/issues?labels=A-parser&labels=good%20first%20issue"
+//! );
+//! ```
+use crate::percent::{self, EncodeSet};
+use crate::{AurisParseErrorKind, ParseError};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// The value of a template variable
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Value {
+    String(String),
+    List(Vec<String>),
+    /// Key value pairs, expanded in this order
+    Map(Vec<(String, String)>),
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<Vec<&str>> for Value {
+    fn from(items: Vec<&str>) -> Self {
+        Value::List(items.into_iter().map(str::to_string).collect())
+    }
+}
+
+impl From<Vec<(&str, &str)>> for Value {
+    fn from(pairs: Vec<(&str, &str)>) -> Self {
+        Value::Map(
+            pairs
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+}
+
+impl Value {
+    /// Empty lists and maps count as undefined, as the RFC says
+    fn is_defined(&self) -> bool {
+        match self {
+            Value::String(_) => true,
+            Value::List(items) => !items.is_empty(),
+            Value::Map(pairs) => !pairs.is_empty(),
+        }
+    }
+}
+
+/// How a variable in an expression is modified
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Modifier {
+    None,
+    /// `{var:3}`, only the first characters of a string
+    Prefix(usize),
+    /// `{var*}`, each list item or map pair on its own
+    Explode,
+}
+
+/// The behaviour of an expression's operator, from the table in RFC 6570
+/// appendix A
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Operator {
+    symbol: Option<char>,
+    first: &'static str,
+    separator: &'static str,
+    named: bool,
+    if_empty: &'static str,
+    allow_reserved: bool,
+}
+
+impl Operator {
+    fn new(symbol: Option<char>) -> Option<Operator> {
+        let (first, separator, named, if_empty, allow_reserved) = match symbol {
+            None => ("", ",", false, "", false),
+            Some('+') => ("", ",", false, "", true),
+            Some('.') => (".", ".", false, "", false),
+            Some('/') => ("/", "/", false, "", false),
+            Some(';') => (";", ";", true, "", false),
+            Some('?') => ("?", "&", true, "=", false),
+            Some('&') => ("&", "&", true, "=", false),
+            Some('#') => ("#", ",", false, "", true),
+            Some(_) => return None,
+        };
+        Some(Operator {
+            symbol,
+            first,
+            separator,
+            named,
+            if_empty,
+            allow_reserved,
+        })
+    }
+
+    fn encode<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        if self.allow_reserved {
+            let reserved = EncodeSet::builder(EncodeSet::UNRESERVED)
+                .keep(":/?#[]@!$&'()*+,;=")
+                .preserve_escapes(true)
+                .build();
+            percent::encode(value, &reserved)
+        } else {
+            percent::encode(value, &EncodeSet::UNRESERVED)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Part {
+    Literal(String),
+    Expression {
+        operator: Operator,
+        variables: Vec<(String, Modifier)>,
+    },
+}
+
+/// A parsed URI template
+///
+/// Displays as the template it was parsed from.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct UriTemplate {
+    parts: Vec<Part>,
+}
+
+/// Whether `name` is a valid `varname`: letters, digits, `_`, escapes,
+/// and `.` between them
+fn is_valid_varname(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.ends_with('.')
+        && !name.contains("..")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '%')
+}
+
+fn parse_varspec(spec: &str) -> Option<(String, Modifier)> {
+    let (name, modifier) = if let Some(name) = spec.strip_suffix('*') {
+        (name, Modifier::Explode)
+    } else if let Some((name, length)) = spec.split_once(':') {
+        let valid_length = !length.is_empty() && length.len() <= 4 && !length.starts_with('0');
+        let length = length.parse::<usize>().ok().filter(|_| valid_length)?;
+        (name, Modifier::Prefix(length))
+    } else {
+        (spec, Modifier::None)
+    };
+    Some((name.to_string(), modifier)).filter(|_| is_valid_varname(name))
+}
+
+impl FromStr for UriTemplate {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError::from(AurisParseErrorKind::Rejected("invalid URI template"));
+        let mut parts = Vec::new();
+        let mut rest = s;
+        while !rest.is_empty() {
+            let start = rest.find(['{', '}']).unwrap_or(rest.len());
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            rest = &rest[start..];
+            if rest.is_empty() {
+                break;
+            }
+            let end = rest
+                .find('}')
+                .filter(|_| rest.starts_with('{'))
+                .ok_or_else(invalid)?;
+            let expression = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            let symbol = expression
+                .chars()
+                .next()
+                .filter(|c| "+./;?&#=,!@|".contains(*c));
+            let operator = Operator::new(symbol).ok_or_else(invalid)?;
+            let variables = expression[symbol.map_or(0, char::len_utf8)..]
+                .split(',')
+                .map(parse_varspec)
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(invalid)?;
+            parts.push(Part::Expression {
+                operator,
+                variables,
+            });
+        }
+        Ok(UriTemplate { parts })
+    }
+}
+
+impl fmt::Display for UriTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => f.write_str(literal)?,
+                Part::Expression {
+                    operator,
+                    variables,
+                } => {
+                    f.write_str("{")?;
+                    if let Some(symbol) = operator.symbol {
+                        write!(f, "{}", symbol)?;
+                    }
+                    for (i, (name, modifier)) in variables.iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(",")?;
+                        }
+                        f.write_str(name)?;
+                        match modifier {
+                            Modifier::None => (),
+                            Modifier::Prefix(length) => write!(f, ":{}", length)?,
+                            Modifier::Explode => f.write_str("*")?,
+                        }
+                    }
+                    f.write_str("}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Append `name=value`, or `name` and the operator's empty form
+fn push_named(out: &mut String, operator: &Operator, name: &str, value: &str) {
+    out.push_str(name);
+    if value.is_empty() {
+        out.push_str(operator.if_empty);
+    } else {
+        out.push('=');
+        out.push_str(value);
+    }
+}
+
+fn expand_variable(
+    out: &mut String,
+    operator: &Operator,
+    name: &str,
+    modifier: Modifier,
+    value: &Value,
+) {
+    let encode = |s: &str| operator.encode(s).into_owned();
+    match (value, modifier) {
+        (Value::String(s), _) => {
+            let s = match modifier {
+                Modifier::Prefix(length) => s.chars().take(length).collect(),
+                _ => s.clone(),
+            };
+            if operator.named {
+                push_named(out, operator, name, &encode(&s));
+            } else {
+                out.push_str(&encode(&s));
+            }
+        }
+        (Value::List(items), Modifier::Explode) => {
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(operator.separator);
+                }
+                if operator.named {
+                    push_named(out, operator, name, &encode(item));
+                } else {
+                    out.push_str(&encode(item));
+                }
+            }
+        }
+        (Value::Map(pairs), Modifier::Explode) => {
+            for (i, (k, v)) in pairs.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(operator.separator);
+                }
+                if operator.named {
+                    push_named(out, operator, &encode(k), &encode(v));
+                } else {
+                    out.push_str(&encode(k));
+                    out.push('=');
+                    out.push_str(&encode(v));
+                }
+            }
+        }
+        // without explode, and a prefix doesn't apply to composite values
+        (Value::List(items), _) => {
+            let items: Vec<String> = items.iter().map(|item| encode(item)).collect();
+            push_joined(out, operator, name, &items);
+        }
+        (Value::Map(pairs), _) => {
+            let items: Vec<String> = pairs
+                .iter()
+                .flat_map(|(k, v)| vec![encode(k), encode(v)])
+                .collect();
+            push_joined(out, operator, name, &items);
+        }
+    }
+}
+
+/// Append the items of a list or map joined with `,`, after `name=` for
+/// named operators
+fn push_joined(out: &mut String, operator: &Operator, name: &str, items: &[String]) {
+    if operator.named {
+        out.push_str(name);
+        out.push('=');
+    }
+    out.push_str(&items.join(","));
+}
+
+impl UriTemplate {
+    /// Expand the template with the variables in `vars`
+    ///
+    /// Undefined variables, and empty lists and maps, are left out along
+    /// with their separators.
+    pub fn expand(&self, vars: &HashMap<&str, Value>) -> String {
+        let reserved = Operator::new(Some('+')).expect("`+` is an operator");
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => out.push_str(&reserved.encode(literal)),
+                Part::Expression {
+                    operator,
+                    variables,
+                } => {
+                    let defined = variables.iter().filter_map(|(name, modifier)| {
+                        let value = vars.get(name.as_str()).filter(|v| v.is_defined())?;
+                        Some((name, *modifier, value))
+                    });
+                    for (i, (name, modifier, value)) in defined.enumerate() {
+                        out.push_str(if i == 0 {
+                            operator.first
+                        } else {
+                            operator.separator
+                        });
+                        expand_variable(&mut out, operator, name, modifier, value);
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The variables of the examples in RFC 6570 section 3.2
+    fn rfc_vars() -> HashMap<&'static str, Value> {
+        let mut vars = HashMap::new();
+        vars.insert("count", Value::from(vec!["one", "two", "three"]));
+        vars.insert("dom", Value::from(vec!["example", "com"]));
+        vars.insert("dub", Value::from("me/too"));
+        vars.insert("hello", Value::from("Hello World!"));
+        vars.insert("half", Value::from("50%"));
+        vars.insert("var", Value::from("value"));
+        vars.insert("who", Value::from("fred"));
+        vars.insert("base", Value::from("http://example.com/home/"));
+        vars.insert("path", Value::from("/foo/bar"));
+        vars.insert("list", Value::from(vec!["red", "green", "blue"]));
+        vars.insert(
+            "keys",
+            Value::from(vec![("semi", ";"), ("dot", "."), ("comma", ",")]),
+        );
+        vars.insert("v", Value::from("6"));
+        vars.insert("x", Value::from("1024"));
+        vars.insert("y", Value::from("768"));
+        vars.insert("empty", Value::from(""));
+        vars.insert("empty_keys", Value::Map(Vec::new()));
+        vars
+    }
+
+    fn expand(template: &str) -> String {
+        template.parse::<UriTemplate>().unwrap().expand(&rfc_vars())
+    }
+
+    #[test]
+    fn test_rfc_examples() {
+        let cases = [
+            ("{var}", "value"),
+            ("{hello}", "Hello%20World%21"),
+            ("{half}", "50%25"),
+            ("O{empty}X", "OX"),
+            ("O{undef}X", "OX"),
+            ("{x,y}", "1024,768"),
+            ("{x,hello,y}", "1024,Hello%20World%21,768"),
+            ("?{x,empty}", "?1024,"),
+            ("?{x,undef}", "?1024"),
+            ("{var:3}", "val"),
+            ("{var:30}", "value"),
+            ("{list}", "red,green,blue"),
+            ("{list*}", "red,green,blue"),
+            ("{keys}", "semi,%3B,dot,.,comma,%2C"),
+            ("{keys*}", "semi=%3B,dot=.,comma=%2C"),
+            ("{+path:6}/here", "/foo/b/here"),
+            ("{+half}", "50%25"),
+            ("{base}index", "http%3A%2F%2Fexample.com%2Fhome%2Findex"),
+            ("{+base}index", "http://example.com/home/index"),
+            ("{+keys*}", "semi=;,dot=.,comma=,"),
+            ("{#path:6}/here", "#/foo/b/here"),
+            ("{#list*}", "#red,green,blue"),
+            ("X{.var:3}", "X.val"),
+            ("X{.list*}", "X.red.green.blue"),
+            ("X{.keys*}", "X.semi=%3B.dot=..comma=%2C"),
+            ("X{.empty_keys*}", "X"),
+            ("{/var:1,var}", "/v/value"),
+            ("{/list*,path:4}", "/red/green/blue/%2Ffoo"),
+            ("{/keys*}", "/semi=%3B/dot=./comma=%2C"),
+            ("{;hello:5}", ";hello=Hello"),
+            ("{;list}", ";list=red,green,blue"),
+            ("{;list*}", ";list=red;list=green;list=blue"),
+            ("{;keys*}", ";semi=%3B;dot=.;comma=%2C"),
+            ("{;x,y,empty}", ";x=1024;y=768;empty"),
+            ("{?var:3}", "?var=val"),
+            ("{?list*}", "?list=red&list=green&list=blue"),
+            ("{?keys}", "?keys=semi,%3B,dot,.,comma,%2C"),
+            ("{?keys*}", "?semi=%3B&dot=.&comma=%2C"),
+            ("{?x,y,empty}", "?x=1024&y=768&empty="),
+            ("?fixed=yes{&x}", "?fixed=yes&x=1024"),
+            ("{&list*}", "&list=red&list=green&list=blue"),
+            ("{&var:3}", "&var=val"),
+        ];
+        for (template, expected) in cases.iter() {
+            assert_eq!(expand(template), *expected, "{}", template);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors_and_display() {
+        for template in &[
+            "{",
+            "}",
+            "{}",
+            "{var",
+            "{a b}",
+            "{var:0}",
+            "{var:10000}",
+            "{=var}",
+            "{.}",
+        ] {
+            assert!(template.parse::<UriTemplate>().is_err(), "{}", template);
+        }
+        let template = "http://h/{+path}{?list*,var:3}#x";
+        assert_eq!(
+            template.parse::<UriTemplate>().unwrap().to_string(),
+            template
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! Removal of tracking parameters from query strings, and unwrapping of
+//! redirect links
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::URI;
+//!
+//! let mut uri = "https://example.com/post?utm_source=feed&fbclid=abc"
+//!     .parse::<URI<String>>()
+//!     .unwrap();
+//! uri.strip_tracking_params();
+//!
+//! assert_eq!("https://example.com/post", format!("{}", uri));
+//! ```
+use crate::{percent, URI};
+
+/// Query parameters removed by default
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "twclid", "ttclid",
+    "igshid", "mc_cid", "mc_eid", "_ga", "_gl", "_hsenc", "_hsmi", "mkt_tok",
+];
+
+/// Query parameter prefixes removed by default
+const TRACKING_PREFIXES: &[&str] = &["utm_"];
+
+/// Query parameters holding the destination of a redirect link, as in
+/// `l.php?u=…` and `/login?redirect_uri=…`
+const REDIRECT_PARAMS: &[&str] = &[
+    "url",
+    "u",
+    "q",
+    "redirect",
+    "redirect_uri",
+    "redirect_url",
+    "target",
+    "dest",
+    "destination",
+    "next",
+    "continue",
+    "return_to",
+    "goto",
+];
+
+/// How many redirect links are unwrapped by default
+const REDIRECT_DEPTH: usize = 5;
+
+/// Rules deciding which query parameters are tracking parameters
+///
+/// The default rules cover the common analytics and ad click identifiers,
+/// more can be added on top of them.
+///
+/// # Examples
+///
+/// ```
+/// use auris::tracking::TrackingRules;
+///
+/// let rules = TrackingRules::default().param("ref").prefix("pk_");
+/// assert!(rules.is_tracking("utm_medium"));
+/// assert!(rules.is_tracking("ref"));
+/// assert!(rules.is_tracking("pk_campaign"));
+/// assert!(!rules.is_tracking("page"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrackingRules {
+    params: Vec<String>,
+    prefixes: Vec<String>,
+}
+
+impl Default for TrackingRules {
+    fn default() -> Self {
+        TrackingRules {
+            params: TRACKING_PARAMS.iter().map(|p| (*p).to_string()).collect(),
+            prefixes: TRACKING_PREFIXES.iter().map(|p| (*p).to_string()).collect(),
+        }
+    }
+}
+
+impl TrackingRules {
+    /// Rules without any of the built in parameters
+    pub fn empty() -> Self {
+        TrackingRules {
+            params: Vec::new(),
+            prefixes: Vec::new(),
+        }
+    }
+
+    /// Also treat the parameter `name` as tracking
+    pub fn param(mut self, name: &str) -> Self {
+        self.params.push(name.to_string());
+        self
+    }
+
+    /// Also treat every parameter starting with `prefix` as tracking
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefixes.push(prefix.to_string());
+        self
+    }
+
+    /// Whether the query parameter `key` is a tracking parameter
+    pub fn is_tracking(&self, key: &str) -> bool {
+        self.params.iter().any(|p| p == key)
+            || self.prefixes.iter().any(|p| key.starts_with(p.as_str()))
+    }
+}
+
+/// Rules deciding which query parameters of a link hold the URI it
+/// redirects to, and how many nested links to unwrap
+///
+/// # Examples
+///
+/// ```
+/// use auris::tracking::RedirectRules;
+/// use auris::URI;
+///
+/// let rules = RedirectRules::empty().param("to").max_depth(1);
+/// let uri = "https://r.example/?to=https%3A%2F%2Fexample.com%2F".parse::<URI<String>>().unwrap();
+///
+/// assert_eq!("https://example.com/", format!("{}", uri.unwrap_redirects_with(&rules)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RedirectRules {
+    params: Vec<String>,
+    max_depth: usize,
+}
+
+impl Default for RedirectRules {
+    fn default() -> Self {
+        RedirectRules {
+            params: REDIRECT_PARAMS.iter().map(|p| (*p).to_string()).collect(),
+            max_depth: REDIRECT_DEPTH,
+        }
+    }
+}
+
+impl RedirectRules {
+    /// Rules without any of the built in parameters
+    pub fn empty() -> Self {
+        RedirectRules {
+            params: Vec::new(),
+            max_depth: REDIRECT_DEPTH,
+        }
+    }
+
+    /// Also look for a destination in the parameter `name`, after the
+    /// parameters already added
+    pub fn param(mut self, name: &str) -> Self {
+        self.params.push(name.to_string());
+        self
+    }
+
+    /// Unwrap at most `depth` nested links
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// The destination `uri` redirects to, `None` if it isn't a redirect
+    /// link
+    fn destination(&self, uri: &URI<String>) -> Option<URI<String>> {
+        let qs = uri.qs.as_ref()?;
+        self.params
+            .iter()
+            .filter_map(|param| qs.get(param.as_str()))
+            .filter_map(|value| percent::decode(value).parse::<URI<String>>().ok())
+            .find(|destination| destination.has_authority())
+    }
+}
+
+impl URI<String> {
+    /// The URI a redirect or tracking link leads to, using the default
+    /// [`RedirectRules`]
+    ///
+    /// Links nested inside each other are unwrapped in turn, up to the
+    /// depth limit. Only destinations with an authority are followed, so
+    /// a `javascript:` or relative destination leaves the link as it is. A
+    /// URI that isn't a redirect link is returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    ///
+    /// let link = "https://l.facebook.com/l.php?u=https%3A%2F%2Fexample.com%2Fpost&h=AT0"
+    ///     .parse::<URI<String>>()
+    ///     .unwrap();
+    /// assert_eq!("https://example.com/post", format!("{}", link.unwrap_redirects()));
+    /// ```
+    pub fn unwrap_redirects(&self) -> URI<String> {
+        self.unwrap_redirects_with(&RedirectRules::default())
+    }
+
+    /// The URI a redirect or tracking link leads to, according to `rules`
+    pub fn unwrap_redirects_with(&self, rules: &RedirectRules) -> URI<String> {
+        let mut uri = self.clone();
+        for _ in 0..rules.max_depth {
+            match rules.destination(&uri) {
+                Some(destination) => uri = destination,
+                None => break,
+            }
+        }
+        uri
+    }
+
+    /// Remove the default tracking parameters from the query string
+    ///
+    /// The query is dropped entirely when no parameters are left.
+    pub fn strip_tracking_params(&mut self) {
+        self.strip_tracking_params_with(&TrackingRules::default())
+    }
+
+    /// Remove the query parameters matching `rules`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    /// use auris::tracking::TrackingRules;
+    ///
+    /// let mut uri = "https://example.com/?ref=home&id=1".parse::<URI<String>>().unwrap();
+    /// uri.strip_tracking_params_with(&TrackingRules::empty().param("ref"));
+    ///
+    /// assert_eq!("https://example.com/?id=1", format!("{}", uri));
+    /// ```
+    pub fn strip_tracking_params_with(&mut self, rules: &TrackingRules) {
+        self.retain_query(|k, _| !rules.is_tracking(k))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_strip_tracking_params() {
+        let mut uri = "https://example.com/a?utm_source=x&utm_campaign=y&gclid=z&q=rust"
+            .parse::<URI<String>>()
+            .unwrap();
+        uri.strip_tracking_params();
+
+        let query_string_map = [("q".to_string(), "rust".to_string())]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(uri.qs, Some(query_string_map));
+        assert_eq!(uri.path, Some(vec!["a".to_string()]));
+    }
+
+    #[test]
+    fn test_unwrap_redirects() {
+        let unwrap = |s: &str, rules: &RedirectRules| {
+            let uri = s.parse::<URI<String>>().unwrap();
+            uri.unwrap_redirects_with(rules).to_string()
+        };
+        let rules = RedirectRules::default();
+        // a link inside a link, the inner one encoded twice
+        let nested = "https://t.example/?url=https%3A%2F%2Fr.example%2F%3Fnext%3Dhttps%253A%252F%252Fexample.com%252Fa";
+        assert_eq!(unwrap(nested, &rules), "https://example.com/a");
+        assert_eq!(
+            unwrap(nested, &rules.clone().max_depth(1)),
+            "https://r.example/?next=https%3A%2F%2Fexample.com%2Fa"
+        );
+        assert_eq!(unwrap(nested, &rules.clone().max_depth(0)), nested);
+        assert_eq!(
+            unwrap("https://g.example/search?q=rust+lang", &rules),
+            "https://g.example/search?q=rust+lang"
+        );
+        assert_eq!(
+            unwrap("https://r.example/?u=javascript%3Aalert(1)", &rules),
+            "https://r.example/?u=javascript%3Aalert(1)"
+        );
+        assert_eq!(
+            unwrap("https://r.example/?u=%2Flocal", &rules),
+            "https://r.example/?u=%2Flocal"
+        );
+        assert_eq!(
+            unwrap(
+                "https://r.example/?to=http://a.example",
+                &RedirectRules::empty().param("to")
+            ),
+            "http://a.example"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Walking and transforming the components of a URI
+//!
+//! # Examples
+//!
+//! ```
+//! use auris::URI;
+//! use auris::visit::Component;
+//!
+//! let mut uri = "http://example.com/Docs/API?Q=x#Top".parse::<URI<String>>().unwrap();
+//! uri.map_components(|component, value| {
+//!     if component == Component::PathSegment {
+//!         *value = value.to_lowercase();
+//!     }
+//! });
+//!
+//! assert_eq!("http://example.com/docs/api?Q=x#Top", format!("{}", uri));
+//! ```
+use crate::URI;
+
+/// The component of a URI being visited
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Component {
+    Scheme,
+    /// The user of the userinfo, not visited so credentials aren't
+    /// rewritten by accident, but reported by
+    /// [`security_findings`](crate::URI::security_findings)
+    User,
+    /// The password of the userinfo, reported like [`Component::User`]
+    Password,
+    Host,
+    PathSegment,
+    QueryKey,
+    QueryValue,
+    Fragment,
+}
+
+/// Visits the components of a URI, allowing each to be changed in place
+///
+/// Every method does nothing by default, so only the components of interest
+/// need to be implemented.
+pub trait UriVisitor {
+    fn visit_scheme(&mut self, _scheme: &mut String) {}
+    fn visit_host(&mut self, _host: &mut String) {}
+    fn visit_path_segment(&mut self, _segment: &mut String) {}
+    fn visit_query_pair(&mut self, _key: &mut String, _value: &mut String) {}
+    fn visit_fragment(&mut self, _fragment: &mut String) {}
+}
+
+/// Adapts a closure over every component to a visitor
+struct ComponentMapper<F>(F);
+
+impl<F> UriVisitor for ComponentMapper<F>
+where
+    F: FnMut(Component, &mut String),
+{
+    fn visit_scheme(&mut self, scheme: &mut String) {
+        (self.0)(Component::Scheme, scheme)
+    }
+
+    fn visit_host(&mut self, host: &mut String) {
+        (self.0)(Component::Host, host)
+    }
+
+    fn visit_path_segment(&mut self, segment: &mut String) {
+        (self.0)(Component::PathSegment, segment)
+    }
+
+    fn visit_query_pair(&mut self, key: &mut String, value: &mut String) {
+        (self.0)(Component::QueryKey, key);
+        (self.0)(Component::QueryValue, value);
+    }
+
+    fn visit_fragment(&mut self, fragment: &mut String) {
+        (self.0)(Component::Fragment, fragment)
+    }
+}
+
+impl URI<String> {
+    /// Walk the scheme, host, path segments, query pairs and fragment in order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use auris::URI;
+    /// use auris::visit::UriVisitor;
+    ///
+    /// struct Redact;
+    ///
+    /// impl UriVisitor for Redact {
+    ///     fn visit_query_pair(&mut self, key: &mut String, value: &mut String) {
+    ///         if key == "token" {
+    ///             *value = "xxx".to_string();
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut uri = "https://example.com/?token=secret".parse::<URI<String>>().unwrap();
+    /// uri.visit(&mut Redact);
+    ///
+    /// assert_eq!("https://example.com/?token=xxx", format!("{}", uri));
+    /// ```
+    pub fn visit<V>(&mut self, visitor: &mut V)
+    where
+        V: UriVisitor + ?Sized,
+    {
+        visitor.visit_scheme(&mut self.scheme);
+        visitor.visit_host(&mut self.authority.host);
+        if let Some(path) = self.path.as_mut() {
+            for segment in path.iter_mut() {
+                visitor.visit_path_segment(segment);
+            }
+        }
+        if let Some(qs) = self.qs.take() {
+            // keys may change, so the map is rebuilt
+            self.qs = Some(
+                qs.into_iter()
+                    .map(|(mut k, mut v)| {
+                        visitor.visit_query_pair(&mut k, &mut v);
+                        (k, v)
+                    })
+                    .collect(),
+            );
+        }
+        if let Some(fragment) = self.fragment.as_mut() {
+            visitor.visit_fragment(fragment);
+        }
+    }
+
+    /// Call `f` with every component of the URI, which it may change in place
+    pub fn map_components<F>(&mut self, f: F)
+    where
+        F: FnMut(Component, &mut String),
+    {
+        self.visit(&mut ComponentMapper(f))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_map_components_order() {
+        let mut uri = "s://h/a/b?k=v#f".parse::<URI<String>>().unwrap();
+        let mut seen = Vec::new();
+        uri.map_components(|component, value| {
+            seen.push((component, value.clone()));
+            value.make_ascii_uppercase();
+        });
+
+        assert_eq!(
+            seen,
+            vec![
+                (Component::Scheme, "s".to_string()),
+                (Component::Host, "h".to_string()),
+                (Component::PathSegment, "a".to_string()),
+                (Component::PathSegment, "b".to_string()),
+                (Component::QueryKey, "k".to_string()),
+                (Component::QueryValue, "v".to_string()),
+                (Component::Fragment, "f".to_string()),
+            ]
+        );
+        assert_eq!("S://H/A/B?K=V#F", format!("{}", uri));
+    }
+}